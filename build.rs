@@ -0,0 +1,190 @@
+//! Embeds `catalog/issues.toml` as `$OUT_DIR/catalog.rs`, see `src/catalog.rs`.
+
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use serde::Deserialize;
+
+const CATALOG: &str = "catalog/issues.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Catalog {
+    issue: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    id: String,
+    code: String,
+    title: String,
+    description: Option<String>,
+    category: Option<String>,
+    severity: Option<String>,
+    cwe: Option<u32>,
+    misra: Option<String>,
+    misra_since: Option<String>,
+    cert: Option<String>,
+    #[serde(default)]
+    compiler_only: bool,
+    #[serde(default = "checked")]
+    checked: bool,
+}
+
+fn checked() -> bool {
+    true
+}
+
+fn variant(field: &str, value: &str, variants: &[(&str, &str)]) -> String {
+    match variants.iter().find(|(name, _)| *name == value) {
+        Some((_, variant)) => variant.to_string(),
+        None => panic!("{CATALOG}: unknown {field} `{value}`"),
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={CATALOG}");
+    let src = std::fs::read_to_string(CATALOG).unwrap();
+    let catalog: Catalog = toml::from_str(&src).unwrap_or_else(|err| panic!("{CATALOG}: {err}"));
+
+    let mut ids = BTreeMap::new();
+    let mut codes = BTreeMap::new();
+    for (index, entry) in catalog.issue.iter().enumerate() {
+        if ids.insert(entry.id.as_str(), index).is_some() {
+            panic!("{CATALOG}: duplicate id `{}`", entry.id);
+        }
+        // see `catalog::UNMAPPED`
+        if entry.code == "CXX-W3999" {
+            panic!(
+                "{CATALOG}: `{}` uses the reserved code `CXX-W3999`",
+                entry.id
+            );
+        }
+        if let Some(other) = codes.insert(entry.code.as_str(), index) {
+            panic!(
+                "{CATALOG}: `{}` and `{}` share the code `{}`",
+                catalog.issue[other].id, entry.id, entry.code
+            );
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "static ENTRIES: [Entry<'static>; {}] = [",
+        catalog.issue.len()
+    )
+    .unwrap();
+    for entry in &catalog.issue {
+        let misra = entry.misra.as_ref().map(|m| {
+            variant(
+                "misra classification",
+                m,
+                &[
+                    ("mandatory", "Mandatory"),
+                    ("required", "Required"),
+                    ("advisory", "Advisory"),
+                ],
+            )
+        });
+        // MISRA rules default to the category and severity of their classification
+        let category = match (&entry.category, &misra) {
+            (Some(category), _) => format!(
+                "Category::{}",
+                variant(
+                    "category",
+                    category,
+                    &[
+                        ("bug-risk", "BugRisk"),
+                        ("antipattern", "Antipattern"),
+                        ("performance", "Performance"),
+                        ("security", "Security"),
+                        ("style", "Style"),
+                    ],
+                )
+            ),
+            (None, Some(m)) => format!("Classification::{m}.category()"),
+            (None, None) => panic!("{CATALOG}: `{}` has no category", entry.id),
+        };
+        let severity = match (&entry.severity, &misra) {
+            (Some(s), _) => Some(format!(
+                "Severity::{}",
+                variant(
+                    "severity",
+                    s,
+                    &[
+                        ("critical", "Critical"),
+                        ("major", "Major"),
+                        ("minor", "Minor"),
+                    ],
+                )
+            )),
+            (None, Some(m)) => Some(format!("Classification::{m}.severity()")),
+            (None, None) => None,
+        };
+        let misra_since = match (&misra, &entry.misra_since) {
+            (None, None) => None,
+            (None, Some(_)) => panic!("{CATALOG}: `{}` is not a MISRA rule", entry.id),
+            (Some(_), since) => Some(variant(
+                "MISRA version",
+                since.as_deref().unwrap_or("2012"),
+                &[("2012", "C2012"), ("2023", "C2023")],
+            )),
+        };
+        if !entry.checked && (misra.is_none() || entry.compiler_only) {
+            panic!(
+                "{CATALOG}: `{}`: only MISRA guidelines not left to the compiler can be unchecked",
+                entry.id
+            );
+        }
+        if entry.cert.is_some() != entry.id.starts_with("cert-") {
+            panic!(
+                "{CATALOG}: `{}` needs a `cert` rule exactly when it is a CERT check",
+                entry.id
+            );
+        }
+        writeln!(
+            out,
+            "    Entry {{ id: {:?}, code: {:?}, title: {:?}, description: {:?}, \
+             category: {category}, severity: {}, cwe: {:?}, misra: {}, \
+             misra_since: {}, cert: {:?}, compiler_only: {}, checked: {} }},",
+            entry.id,
+            entry.code,
+            entry.title,
+            entry.description,
+            severity.map_or("None".to_string(), |s| format!("Some({s})")),
+            entry.cwe,
+            misra.map_or("None".to_string(), |m| format!("Some(Classification::{m})")),
+            misra_since.map_or("None".to_string(), |v| format!("Some(MisraVersion::{v})")),
+            entry.cert,
+            entry.compiler_only,
+            entry.checked,
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let mut by_id = phf_codegen::Map::new();
+    for (id, index) in &ids {
+        by_id.entry(*id, &index.to_string());
+    }
+    writeln!(
+        out,
+        "static BY_ID: phf::Map<&'static str, usize> = {};",
+        by_id.build()
+    )
+    .unwrap();
+    let mut by_code = phf_codegen::Map::new();
+    for (code, index) in &codes {
+        by_code.entry(*code, &index.to_string());
+    }
+    writeln!(
+        out,
+        "static BY_CODE: phf::Map<&'static str, usize> = {};",
+        by_code.build()
+    )
+    .unwrap();
+
+    let dest = Path::new(&std::env::var("OUT_DIR").unwrap()).join("catalog.rs");
+    std::fs::write(dest, out).unwrap();
+}
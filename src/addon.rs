@@ -0,0 +1,561 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    catalog::{self, CustomEntry, Entry},
+    config,
+    language::Language,
+};
+
+/// A cppcheck addon shipped with cppcheck.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Addon {
+    /// MISRA C, `misra.py`.
+    Misra,
+    /// SEI CERT C, `cert.py`.
+    Cert,
+    /// Calls to libc functions which are not thread safe and local statics, `threadsafety.py`.
+    Threadsafety,
+    /// Uses of 32-bit `time_t`, `y2038.py`. Not run when the project builds with 64-bit
+    /// `time_t`.
+    Y2038,
+    /// Naming conventions, `naming.py`, run when [`Naming`] conventions are configured.
+    #[serde(skip)]
+    Naming,
+}
+
+impl Addon {
+    pub const ALL: [Addon; 5] = [
+        Addon::Misra,
+        Addon::Cert,
+        Addon::Threadsafety,
+        Addon::Y2038,
+        Addon::Naming,
+    ];
+
+    /// Value accepted by cppcheck's `--addon` flag.
+    pub fn as_cppcheck_arg(self) -> &'static str {
+        match self {
+            Addon::Misra => "misra",
+            Addon::Cert => "cert",
+            Addon::Threadsafety => "threadsafety",
+            Addon::Y2038 => "y2038",
+            Addon::Naming => "naming",
+        }
+    }
+
+    /// Prefix cppcheck gives the ids of the addon's findings.
+    pub fn id_prefix(self) -> &'static str {
+        match self {
+            Addon::Misra => "misra-",
+            Addon::Cert => "cert-",
+            Addon::Threadsafety => "threadsafety-",
+            Addon::Y2038 => "y2038-",
+            Addon::Naming => "naming-",
+        }
+    }
+
+    /// The addon reporting the cppcheck id `id`, if any.
+    pub fn of(id: &str) -> Option<Addon> {
+        Addon::ALL
+            .into_iter()
+            .find(|addon| id.starts_with(addon.id_prefix()))
+    }
+
+    /// Whether the addon checks translation units in `language`.
+    pub fn applies_to(self, language: Option<Language>) -> bool {
+        match self {
+            // MISRA C:2012 and CERT C do not apply to C++ translation units
+            Addon::Misra | Addon::Cert => language == Some(Language::C),
+            Addon::Threadsafety | Addon::Y2038 | Addon::Naming => true,
+        }
+    }
+}
+
+/// Naming conventions checked by the naming addon, as Python regular expressions matched
+/// from the start of identifiers, end them with `$` to match whole identifiers.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
+pub struct Naming {
+    /// Names of variables, local and global alike, e.g. `^(g_)?[a-z][a-z0-9_]*$`.
+    #[serde(default)]
+    pub variables: Option<String>,
+    /// Names of functions, e.g. `^[a-z][a-z0-9_]*$`.
+    #[serde(default)]
+    pub functions: Option<String>,
+    /// Names of private member variables of C++ classes, e.g. `^m_`.
+    #[serde(default)]
+    pub private_members: Option<String>,
+    /// Names of `const` variables, e.g. `^[A-Z][A-Z0-9_]*$`.
+    #[serde(default)]
+    pub constants: Option<String>,
+}
+
+impl Naming {
+    /// The `naming.py` flag of each convention, with the id of the check enforcing it.
+    fn conventions(&self) -> [(&'static str, &'static str, &Option<String>); 4] {
+        [
+            ("--var", "naming-varname", &self.variables),
+            ("--function", "naming-functionName", &self.functions),
+            (
+                "--private-member-variable",
+                "naming-privateMemberVariable",
+                &self.private_members,
+            ),
+            ("--const", "naming-constname", &self.constants),
+        ]
+    }
+
+    /// The addon's JSON config, passed to `--addon` in place of the script.
+    pub fn addon_json(&self) -> String {
+        let args: Vec<_> = self
+            .conventions()
+            .into_iter()
+            .filter_map(|(flag, _, pattern)| Some(format!("{flag}={}", pattern.as_ref()?)))
+            .collect();
+        serde_json::json!({ "script": "naming.py", "args": args }).to_string()
+    }
+
+    /// The pattern identifiers reported by the check `id` are expected to match.
+    pub fn pattern(&self, id: &str) -> Option<&str> {
+        self.conventions()
+            .into_iter()
+            .find(|(_, check, _)| *check == id)
+            .and_then(|(_, _, pattern)| pattern.as_deref())
+    }
+
+    /// cppcheck ids of the checks with a convention to enforce.
+    pub fn checks(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.conventions()
+            .into_iter()
+            .filter(|(_, _, pattern)| pattern.is_some())
+            .map(|(_, check, _)| check)
+    }
+}
+
+/// An addon of the repository, for house rules.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
+pub struct CustomAddon {
+    /// Python script, or addon JSON file naming its `script`, relative to the repository root.
+    /// Both must lie in the repository, unless the script is one shipped with cppcheck.
+    pub path: PathBuf,
+    /// Issue codes by prefix of the ids of the addon's findings, which cppcheck prefixes
+    /// with the script name, e.g. `{"house-goto": "CXX-W4001"}`. The longest prefix wins.
+    #[serde(default)]
+    pub codes: BTreeMap<String, String>,
+    /// Issue code of the findings none of `codes` matches. Without it, they are reported
+    /// as unmapped checks.
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+/// The addons of the repository, with catalog entries for the codes of their findings.
+#[derive(Debug, Default)]
+pub struct CustomAddons {
+    /// Absolute paths to pass to `--addon`.
+    pub paths: Vec<PathBuf>,
+    /// Entries by id prefix, longest first.
+    entries: Vec<CustomEntry>,
+}
+
+impl CustomAddons {
+    pub fn load(code_path: &Path, addons: &[CustomAddon]) -> Result<Self, String> {
+        let mut custom = CustomAddons::default();
+        // code -> the addon using it
+        let mut codes = BTreeMap::<&str, &Path>::new();
+        for addon in addons {
+            let invalid = |what: String| format!("addon `{}`: {what}", addon.path.display());
+            let path = config::repository_file(code_path, &addon.path).map_err(invalid)?;
+            let name = script_name(code_path, &path).map_err(invalid)?;
+            if let Some(prefix) = addon
+                .codes
+                .keys()
+                .find(|p| !p.starts_with(&format!("{name}-")))
+            {
+                return Err(invalid(format!(
+                    "`{prefix}` does not start with `{name}-`, the prefix of the ids of its findings"
+                )));
+            }
+            let generic = addon.code.iter().map(|code| (format!("{name}-"), code));
+            for (prefix, code) in addon
+                .codes
+                .iter()
+                .map(|(p, c)| (p.clone(), c))
+                .chain(generic)
+            {
+                if catalog::by_code(code).is_some() {
+                    return Err(invalid(format!("`{code}` is the code of a built-in check")));
+                }
+                match codes.insert(code, &addon.path) {
+                    Some(other) if other != addon.path => {
+                        return Err(invalid(format!(
+                            "`{code}` is also used by addon `{}`",
+                            other.display()
+                        )))
+                    }
+                    _ => {}
+                }
+                custom.entries.push(CustomEntry {
+                    id: prefix,
+                    code: code.clone(),
+                    title: format!("Finding of the `{name}` addon"),
+                    description: None,
+                    prefix: true,
+                });
+            }
+            custom.paths.push(path);
+        }
+        custom
+            .entries
+            .sort_by_key(|e| std::cmp::Reverse(e.id.len()));
+        Ok(custom)
+    }
+
+    /// The entry for the cppcheck id `id` of a finding of a repository addon.
+    pub fn lookup(&self, id: &str) -> Option<Entry<'_>> {
+        self.entries
+            .iter()
+            .find(|e| e.matches(id))
+            .map(CustomEntry::entry)
+    }
+
+    pub fn entries(&self) -> &[CustomEntry] {
+        &self.entries
+    }
+}
+
+/// The name cppcheck prefixes the ids of the addon at `path` with, that of its script.
+/// The script an addon JSON file names must be one of cppcheck's, or lie in the repository.
+fn script_name(code_path: &Path, path: &Path) -> Result<String, String> {
+    let script = if path.extension().is_some_and(|e| e == "json") {
+        let src = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let config: serde_json::Value =
+            serde_json::from_str(&src).map_err(|err| err.to_string())?;
+        let script = config["script"]
+            .as_str()
+            .ok_or("the addon JSON file has no `script`")?;
+        // cppcheck looks for the script next to the JSON file, then among its own addons
+        let dir = path.parent().unwrap_or(path);
+        let local = dir.join(script);
+        if local.exists() || Path::new(script).components().count() > 1 {
+            let relative = local.strip_prefix(code_path).unwrap_or(&local);
+            config::repository_file(code_path, relative)?
+        } else {
+            PathBuf::from(script)
+        }
+    } else if path.is_file() {
+        path.to_path_buf()
+    } else {
+        return Err("not a file".to_string());
+    };
+    script
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(String::from)
+        .ok_or_else(|| format!("`{}` is not a script", script.display()))
+}
+
+/// Whether the Python interpreter cppcheck runs addons with is available.
+pub fn python_available() -> bool {
+    std::process::Command::new("python3")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// The addon named by the message of a cppcheck `internalError` about running an addon,
+/// such as `Failed to execute addon 'misra' - exitcode is 1`.
+pub fn failed_addon(msg: &str) -> Option<String> {
+    if let Some((_, rest)) = msg.split_once("addon '") {
+        return rest.split('\'').next().map(String::from);
+    }
+    let failed = msg.contains("Failed to execute") || msg.contains("addon");
+    let script = msg
+        .split(|c: char| c.is_whitespace() || c == '\'' || c == '"')
+        .find_map(|word| script_stem(word.strip_suffix(".py").map(|_| word)?));
+    match script {
+        Some(script) if failed => Some(script),
+        _ => None,
+    }
+}
+
+/// The addons whose Python traceback is in cppcheck's stderr, with the exception raised.
+pub fn tracebacks(stderr: &str) -> Vec<(String, String)> {
+    let mut failures = vec![];
+    let mut lines = stderr.lines().peekable();
+    while let Some(line) = lines.next() {
+        if !line
+            .trim_start()
+            .starts_with("Traceback (most recent call last)")
+        {
+            continue;
+        }
+        // frames are indented, the outermost first, then comes the exception
+        let mut addon = None;
+        while let Some(frame) = lines.next_if(|l| l.starts_with(' ')) {
+            if addon.is_none() {
+                addon = frame
+                    .trim()
+                    .strip_prefix("File \"")
+                    .and_then(|f| f.split('"').next())
+                    .and_then(script_stem);
+            }
+        }
+        let exception = lines.next().unwrap_or_default().trim().to_string();
+        failures.push((addon.unwrap_or_else(|| "unknown".to_string()), exception));
+    }
+    failures
+}
+
+/// The name of an addon script, leaving out the helpers shipped with the addons.
+fn script_stem(path: &str) -> Option<String> {
+    let stem = Path::new(path).file_stem()?.to_str()?;
+    (!matches!(stem, "runaddon" | "cppcheckdata" | "cppcheck")).then(|| stem.to_string())
+}
+
+/// Directories whose build files define `_TIME_BITS=64` or `_USE_TIME_BITS64`, making
+/// `time_t` 64 bits wide for the sources below them.
+#[derive(Debug, Default)]
+pub struct TimeBits64(BTreeMap<PathBuf, PathBuf>);
+
+impl TimeBits64 {
+    /// Reads the build files among `files`, the candidates of file discovery.
+    pub fn scan(files: &[PathBuf]) -> Self {
+        let mut dirs = BTreeMap::new();
+        for file in files.iter().filter(|f| is_build_file(f)) {
+            let Some(dir) = file.parent() else {
+                continue;
+            };
+            if std::fs::read_to_string(file).is_ok_and(|src| enables_time_bits_64(&src)) {
+                dirs.entry(dir.to_path_buf())
+                    .or_insert_with(|| file.clone());
+            }
+        }
+        TimeBits64(dirs)
+    }
+
+    /// The build file making `time_t` 64 bits wide for `file`, in its directory or above.
+    pub fn origin(&self, file: &Path) -> Option<&Path> {
+        file.ancestors()
+            .skip(1)
+            .find_map(|dir| self.0.get(dir))
+            .map(PathBuf::as_path)
+    }
+}
+
+/// The define among `defines` making `time_t` 64 bits wide.
+pub fn defines_time_bits_64(defines: &[String]) -> Option<&String> {
+    defines.iter().find(|d| is_time_bits_64(d))
+}
+
+fn is_time_bits_64(define: &str) -> bool {
+    matches!(
+        define.trim(),
+        "_TIME_BITS=64" | "_USE_TIME_BITS64" | "_USE_TIME_BITS64=1"
+    )
+}
+
+/// Whether a build file defines `_TIME_BITS=64` or `_USE_TIME_BITS64`, outside of comments.
+fn enables_time_bits_64(src: &str) -> bool {
+    src.lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .filter(|line| !line.trim_start().starts_with("dnl"))
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || "()[]\"',;".contains(c)))
+        .any(|token| {
+            let define = token
+                .strip_prefix("-D")
+                .or_else(|| token.strip_prefix("/D"))
+                .unwrap_or(token);
+            is_time_bits_64(define)
+        })
+}
+
+fn is_build_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    matches!(
+        name,
+        "CMakeLists.txt"
+            | "Makefile"
+            | "makefile"
+            | "GNUmakefile"
+            | "Makefile.am"
+            | "meson.build"
+            | "configure.ac"
+    ) || name.ends_with(".cmake")
+        || name.ends_with(".mk")
+}
+
+pub fn default_addons() -> Vec<Addon> {
+    vec![Addon::Misra]
+}
+
+#[test]
+fn t_addon_of() {
+    assert_eq!(Addon::of("misra-c2012-2.3"), Some(Addon::Misra));
+    assert_eq!(Addon::of("cert-EXP46-C"), Some(Addon::Cert));
+    assert_eq!(Addon::of("nullPointer"), None);
+    assert_eq!(Addon::of("y2038-unsafe-call"), Some(Addon::Y2038));
+    assert!(!Addon::Cert.applies_to(Some(Language::Cpp)));
+    assert!(Addon::Threadsafety.applies_to(Some(Language::Cpp)));
+}
+
+#[test]
+fn t_custom_addons() {
+    let dir = tempfile::tempdir().unwrap();
+    let code_path = dir.path().join("code");
+    std::fs::create_dir_all(code_path.join("tools")).unwrap();
+    std::fs::write(code_path.join("tools/house.py"), "").unwrap();
+    std::fs::write(
+        code_path.join("tools/house.json"),
+        r#"{"script": "house.py", "args": ["--strict"]}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        code_path.join("tools/builtin.json"),
+        r#"{"script": "misra.py", "args": ["--rule-texts=rules.txt"]}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        code_path.join("tools/escape.json"),
+        r#"{"script": "../../evil.py"}"#,
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("evil.py"), "").unwrap();
+    let addon = |path: &str, codes: &[(&str, &str)], code: Option<&str>| CustomAddon {
+        path: path.into(),
+        codes: codes
+            .iter()
+            .map(|(p, c)| (p.to_string(), c.to_string()))
+            .collect(),
+        code: code.map(String::from),
+    };
+
+    let custom = CustomAddons::load(
+        &code_path,
+        &[addon(
+            "tools/house.json",
+            &[("house-noGoto", "CXX-W4001")],
+            Some("CXX-W4000"),
+        )],
+    )
+    .unwrap();
+    assert_eq!(
+        custom.paths,
+        [code_path.canonicalize().unwrap().join("tools/house.json")]
+    );
+    assert_eq!(custom.lookup("house-noGotoHere").unwrap().code, "CXX-W4001");
+    assert_eq!(custom.lookup("house-other").unwrap().code, "CXX-W4000");
+    assert!(custom.lookup("misra-c2012-2.3").is_none());
+    let custom = CustomAddons::load(
+        &code_path,
+        &[addon("tools/builtin.json", &[], Some("CXX-W4002"))],
+    )
+    .unwrap();
+    assert_eq!(custom.lookup("misra-x").unwrap().code, "CXX-W4002");
+
+    let err = |addons: &[CustomAddon]| CustomAddons::load(&code_path, addons).unwrap_err();
+    assert_eq!(
+        err(&[addon("tools/house.py", &[], Some("CXX-W3651"))]),
+        "addon `tools/house.py`: `CXX-W3651` is the code of a built-in check"
+    );
+    assert_eq!(
+        err(&[addon("tools/house.py", &[("goto", "CXX-W4001")], None)]),
+        "addon `tools/house.py`: `goto` does not start with `house-`, the prefix of the ids of its findings"
+    );
+    assert_eq!(
+        err(&[
+            addon("tools/house.py", &[], Some("CXX-W4000")),
+            addon("tools/builtin.json", &[], Some("CXX-W4000")),
+        ]),
+        "addon `tools/builtin.json`: `CXX-W4000` is also used by addon `tools/house.py`"
+    );
+    assert_eq!(
+        err(&[addon("../evil.py", &[], None)]),
+        "addon `../evil.py`: `../evil.py` is outside of the repository"
+    );
+    assert!(err(&[addon("tools/escape.json", &[], None)]).ends_with("is outside of the repository"));
+}
+
+#[test]
+fn t_addon_failures() {
+    assert_eq!(
+        failed_addon("Bailing out from analysis: Checking file failed: Failed to execute addon 'misra' - exitcode is 1").as_deref(),
+        Some("misra")
+    );
+    assert_eq!(
+        failed_addon("Failed to execute 'python3 /usr/share/cppcheck/addons/runaddon.py /usr/share/cppcheck/addons/cert.py --cli a.c.dump'").as_deref(),
+        Some("cert")
+    );
+    assert_eq!(failed_addon("Internal error: AST broken"), None);
+
+    let stderr = "Checking a.c ...\nTraceback (most recent call last):\n  File \"/usr/share/cppcheck/addons/runaddon.py\", line 8, in <module>\n    runpy.run_path(addon, run_name='__main__')\n  File \"/usr/share/cppcheck/addons/misra.py\", line 4810, in <module>\n    main()\n  File \"/usr/share/cppcheck/addons/cppcheckdata.py\", line 12, in parse\n    raise\nKeyError: 'valueType'\n1/1 files checked\n";
+    assert_eq!(
+        tracebacks(stderr),
+        [("misra".to_string(), "KeyError: 'valueType'".to_string())]
+    );
+}
+
+#[test]
+fn t_time_bits_64() {
+    assert!(enables_time_bits_64("add_definitions(-D_TIME_BITS=64)"));
+    assert!(enables_time_bits_64(
+        "add_compile_definitions(_TIME_BITS=64 FOO)"
+    ));
+    assert!(enables_time_bits_64("CFLAGS += -D_USE_TIME_BITS64"));
+    assert!(enables_time_bits_64(
+        "add_project_arguments('-D_TIME_BITS=64', language: 'c')"
+    ));
+    assert!(!enables_time_bits_64(
+        "add_definitions(-D_FILE_OFFSET_BITS=64)"
+    ));
+    assert!(!enables_time_bits_64("# add_definitions(-D_TIME_BITS=64)"));
+    assert!(!enables_time_bits_64("CFLAGS += -U_TIME_BITS=64"));
+    assert!(is_build_file(Path::new("src/CMakeLists.txt")));
+    assert!(is_build_file(Path::new("cmake/flags.cmake")));
+    assert!(!is_build_file(Path::new("src/main.c")));
+    assert_eq!(
+        defines_time_bits_64(&["FOO".to_string(), "_TIME_BITS=64".to_string()]).map(String::as_str),
+        Some("_TIME_BITS=64")
+    );
+
+    let dirs = TimeBits64(BTreeMap::from([(
+        PathBuf::from("/code/fw"),
+        PathBuf::from("/code/fw/CMakeLists.txt"),
+    )]));
+    assert_eq!(
+        dirs.origin(Path::new("/code/fw/src/a.c")),
+        Some(Path::new("/code/fw/CMakeLists.txt"))
+    );
+    assert_eq!(dirs.origin(Path::new("/code/app/a.c")), None);
+}
+
+#[test]
+fn t_naming() {
+    let naming = Naming {
+        variables: Some("^[a-z_]+$".to_string()),
+        constants: Some("^[A-Z_]+$".to_string()),
+        ..Naming::default()
+    };
+    assert_eq!(
+        naming.addon_json(),
+        r#"{"args":["--var=^[a-z_]+$","--const=^[A-Z_]+$"],"script":"naming.py"}"#
+    );
+    assert_eq!(naming.pattern("naming-constname"), Some("^[A-Z_]+$"));
+    assert_eq!(naming.pattern("naming-functionName"), None);
+    assert_eq!(
+        naming.checks().collect::<Vec<_>>(),
+        ["naming-varname", "naming-constname"]
+    );
+}
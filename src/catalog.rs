@@ -0,0 +1,199 @@
+//! The cppcheck checks reported by the analyzer, generated by `build.rs` from
+//! `catalog/issues.toml`.
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+
+use crate::result::Severity;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Category {
+    BugRisk,
+    Antipattern,
+    Performance,
+    Security,
+    Style,
+}
+
+/// MISRA C:2012 rule classification.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Classification {
+    Mandatory,
+    Required,
+    Advisory,
+}
+
+impl Classification {
+    /// Severity of violations of rules with the classification.
+    pub const fn severity(self) -> Severity {
+        match self {
+            Classification::Mandatory => Severity::Critical,
+            Classification::Required => Severity::Major,
+            Classification::Advisory => Severity::Minor,
+        }
+    }
+
+    /// Category of rules with the classification.
+    pub const fn category(self) -> Category {
+        match self {
+            Classification::Mandatory | Classification::Required => Category::BugRisk,
+            Classification::Advisory => Category::Antipattern,
+        }
+    }
+}
+
+/// MISRA C edition, MISRA C:2023 incorporating the amendments to MISRA C:2012.
+#[derive(Deserialize, Serialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+pub enum MisraVersion {
+    /// MISRA C:2012 with Amendments 1 and 2.
+    #[default]
+    #[serde(rename = "2012")]
+    C2012,
+    #[serde(rename = "2023")]
+    C2023,
+}
+
+impl MisraVersion {
+    pub fn year(self) -> &'static str {
+        match self {
+            MisraVersion::C2012 => "2012",
+            MisraVersion::C2023 => "2023",
+        }
+    }
+}
+
+/// The issue a cppcheck check is reported as, borrowed from the catalog or from a
+/// [`CustomEntry`].
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<'a> {
+    /// cppcheck error id.
+    pub id: &'a str,
+    /// DeepSource issue code.
+    pub code: &'a str,
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+    /// Derived from the MISRA classification unless set in the catalog.
+    pub category: Category,
+    /// Replaces the severity reported by cppcheck, derived from the MISRA classification
+    /// unless set in the catalog.
+    pub severity: Option<Severity>,
+    pub cwe: Option<u32>,
+    pub misra: Option<Classification>,
+    /// The first MISRA C version with the rule.
+    pub misra_since: Option<MisraVersion>,
+    /// The SEI CERT C rule or recommendation, `EXP46-C`.
+    pub cert: Option<&'a str>,
+    /// A MISRA rule left to the compiler, which cppcheck cannot check.
+    pub compiler_only: bool,
+    /// False for MISRA guidelines the addon has no check for.
+    pub checked: bool,
+}
+
+/// An entry for checks defined by the repository, in its addons or rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomEntry {
+    /// cppcheck id, or the prefix of the ids of an addon's findings.
+    pub id: String,
+    pub code: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// `id` is the prefix of the ids of an addon's findings rather than an id.
+    pub prefix: bool,
+}
+
+impl CustomEntry {
+    pub fn entry(&self) -> Entry<'_> {
+        Entry {
+            id: &self.id,
+            code: &self.code,
+            title: &self.title,
+            description: self.description.as_deref(),
+            category: Category::BugRisk,
+            severity: None,
+            cwe: None,
+            misra: None,
+            misra_since: None,
+            cert: None,
+            compiler_only: false,
+            checked: true,
+        }
+    }
+
+    /// Whether findings with the cppcheck id `id` are reported as this entry.
+    pub fn matches(&self, id: &str) -> bool {
+        match self.prefix {
+            true => id.starts_with(&self.id),
+            false => id == self.id,
+        }
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/catalog.rs"));
+
+/// Catch-all for findings of checks missing from the catalog, reported with `report_unmapped`.
+pub static UNMAPPED: Entry<'static> = Entry {
+    id: "unmapped",
+    code: "CXX-W3999",
+    title: "Unclassified cppcheck finding",
+    description: Some(
+        "cppcheck reported a finding of a check without an issue code of its own. \
+         The cppcheck id of the check is given in brackets at the end of the message.",
+    ),
+    category: Category::BugRisk,
+    severity: None,
+    cwe: None,
+    misra: None,
+    misra_since: None,
+    cert: None,
+    compiler_only: false,
+    checked: true,
+};
+
+/// The entry for a cppcheck error id.
+pub fn lookup(id: &str) -> Option<&'static Entry<'static>> {
+    BY_ID.get(id).map(|&index| &ENTRIES[index])
+}
+
+/// The entry for a DeepSource issue code, [`UNMAPPED`] included.
+pub fn by_code(code: &str) -> Option<&'static Entry<'static>> {
+    if code == UNMAPPED.code {
+        return Some(&UNMAPPED);
+    }
+    BY_CODE.get(code).map(|&index| &ENTRIES[index])
+}
+
+/// Every entry, in catalog order.
+pub fn entries() -> &'static [Entry<'static>] {
+    &ENTRIES
+}
+
+#[test]
+fn t_lookup() {
+    let entry = lookup("nullPointer").unwrap();
+    assert_eq!(entry.code, "CXX-W3651");
+    assert_eq!(entry.cwe, Some(476));
+    assert_eq!(by_code("CXX-W3651").unwrap().id, "nullPointer");
+    for id in ["misra-c2012-17.3", "misra-c2012-22.4"] {
+        assert_eq!(lookup(id).unwrap().misra, Some(Classification::Mandatory));
+    }
+    assert!(lookup("CXX-W3651").is_none());
+    assert_eq!(
+        lookup("misra-c2012-23.1").unwrap().misra_since,
+        Some(MisraVersion::C2023)
+    );
+    let entry = lookup("misra-c2012-9.1").unwrap();
+    assert_eq!(entry.severity, Some(Severity::Critical));
+    assert_eq!(entry.category, Category::BugRisk);
+    assert_eq!(
+        lookup("misra-c2012-15.5").unwrap().severity,
+        Some(Severity::Minor)
+    );
+    let entry = lookup("cert-EXP46-C").unwrap();
+    assert_eq!((entry.cert, entry.cwe), (Some("EXP46-C"), Some(480)));
+    assert_eq!(by_code(UNMAPPED.code).unwrap().id, "unmapped");
+    assert_eq!(entries().len(), BY_CODE.len());
+}
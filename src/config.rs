@@ -1,8 +1,25 @@
-use std::path::PathBuf;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use serde::Deserialize;
 
+use crate::{
+    addon::{self, Addon, CustomAddon, Naming},
+    catalog::{Classification, CustomEntry, MisraVersion},
+    language::{Language, SourceKind},
+    policy::IssuePolicy,
+    result::Severity,
+    rules::{InlineRule, RuleFile},
+    sources::Limits,
+    validation::ConfigValidation,
+};
+
 #[derive(Default, Deserialize, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
 pub struct AnalyzerConfig {
     files: Vec<PathBuf>,
     #[serde(default)]
@@ -10,29 +27,415 @@ pub struct AnalyzerConfig {
 }
 
 impl AnalyzerConfig {
-    pub fn cxx_files(self) -> Vec<PathBuf> {
+    pub fn into_files(self) -> Vec<PathBuf> {
         self.files
-            .into_iter()
-            .filter(|f| !f.is_symlink())
-            .filter(|f| f.is_file())
-            .filter(|f| {
-                f.extension()
-                    .map(|x| x.eq("cpp") | x.eq("c"))
-                    .unwrap_or_default()
-            })
-            // ignore files > ~25MB in size
-            .filter(|f| {
-                !f.metadata()
-                    .map(|x| x.len() > 25_000_000)
-                    .unwrap_or_default()
-            })
-            .collect()
+    }
+
+    /// Listed files which do not exist, with their index in `files`.
+    pub fn missing_files(&self) -> impl Iterator<Item = (usize, &PathBuf)> {
+        self.files.iter().enumerate().filter(|(_, f)| !f.exists())
     }
 }
 
-#[derive(Deserialize, Default, Debug)]
+#[derive(Deserialize, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
 pub struct AnalyzerMeta {
+    #[allow(dead_code)]
     pub name: String,
+    #[allow(dead_code)]
     pub enabled: bool,
     // todo(swarnim): add misra_compliance: bool
+    /// Language standard passed as `--std`, e.g. `c99` or `c++17`.
+    #[serde(default)]
+    pub std: Option<String>,
+    /// Target platform passed as `--platform`, either a cppcheck built-in
+    /// (`unix64`, `avr8`, ...) or a platform XML file relative to the repository root.
+    #[serde(default)]
+    pub platform: Option<String>,
+    /// Force the language of every file with `--language`.
+    #[serde(default)]
+    pub language: Option<Language>,
+    /// Extensions to analyze, on top of the defaults, mapped to `c`, `c++`, `c-header`,
+    /// `c++-header` or `header`. `null` stops analyzing an extension.
+    #[serde(default)]
+    pub extensions: BTreeMap<String, Option<SourceKind>>,
+    /// Per directory overrides, applied in order. Later matches win.
+    #[serde(default)]
+    pub overrides: Vec<Override>,
+    /// Macros passed as `-D`, either `NAME` or `NAME=value`.
+    #[serde(default)]
+    pub defines: Vec<String>,
+    /// Macros passed as `-U`.
+    #[serde(default)]
+    pub undefines: Vec<String>,
+    /// Include directories passed as `-I`, relative to the repository root.
+    #[serde(default)]
+    pub include_paths: Vec<PathBuf>,
+    /// Headers passed as `--include`, relative to the repository root.
+    #[serde(default)]
+    pub forced_includes: Vec<PathBuf>,
+    /// Add directories named `include`, and directories holding headers
+    /// included by the analyzed files, to the include paths.
+    #[serde(default)]
+    pub auto_include_paths: bool,
+    /// cppcheck library configs passed as `--library`, e.g. `posix` or `qt`.
+    #[serde(default)]
+    pub libraries: Vec<String>,
+    /// Enable library configs matching the `<header>` includes of the analyzed files
+    /// which the repository does not provide.
+    #[serde(default = "enabled")]
+    pub auto_libraries: bool,
+    /// Directory, relative to the repository root, holding custom `*.cfg` library configs.
+    #[serde(default)]
+    pub library_dir: Option<PathBuf>,
+    /// How to treat issues in files matching the `test_patterns` of `.deepsource.toml`.
+    #[serde(default)]
+    pub test_issues: TestIssues,
+    /// Files larger than this many bytes are skipped, ~25MB by default.
+    #[serde(default = "max_file_size")]
+    pub max_file_size: u64,
+    /// Files with more lines than this are skipped.
+    #[serde(default)]
+    pub max_lines: Option<usize>,
+    /// Skip files whose heading comments carry a generated code marker such as
+    /// "Code generated by ... DO NOT EDIT.".
+    #[serde(default = "enabled")]
+    pub skip_generated: bool,
+    /// Additional generated code markers, matched case-insensitively in the comments
+    /// heading a file.
+    #[serde(default)]
+    pub generated_markers: Vec<String>,
+    /// Checks not to run, as issue codes (`CXX-W3651`), cppcheck ids (`nullPointer`)
+    /// or globs over either (`misra-c2012-21.*`).
+    #[serde(default)]
+    pub disabled_issues: Vec<String>,
+    /// Only run the checks matching these, in the same format as `disabled_issues`.
+    #[serde(default)]
+    pub enabled_only: Vec<String>,
+    /// Severity to report checks with, keyed like `disabled_issues`. An exact issue
+    /// code or id takes precedence over a glob.
+    #[serde(default)]
+    pub severity_overrides: BTreeMap<String, Severity>,
+    /// cppcheck addons to run: `misra` (MISRA C) and `cert` (SEI CERT C) on C translation
+    /// units, `threadsafety` and `y2038` on all of them. `y2038` is skipped when `defines`
+    /// has `_TIME_BITS=64` or `_USE_TIME_BITS64`, and for the files below a build file
+    /// defining either.
+    #[serde(default = "addon::default_addons")]
+    pub addons: Vec<Addon>,
+    /// Addons of the repository to run on every translation unit.
+    #[serde(default)]
+    pub custom_addons: Vec<CustomAddon>,
+    /// Naming conventions enforced with the naming addon, on C and C++ alike.
+    #[serde(default)]
+    pub naming: Option<Naming>,
+    /// cppcheck rule XML files of the repository, with the issue each rule id is reported
+    /// as. Rule ids without one are reported as unmapped checks.
+    #[serde(default)]
+    pub rule_files: Vec<RuleFile>,
+    /// Rules matching a PCRE `pattern` against the token list, reported as `code`.
+    #[serde(default)]
+    pub rules: Vec<InlineRule>,
+    /// MISRA C rule set and numbering, `2012` (with Amendments 1 and 2) or `2023`.
+    /// Rules of later amendments are only reported with `2023`.
+    #[serde(default)]
+    pub misra_version: MisraVersion,
+    /// Guideline Re-categorization Plan, relative to the repository root: a TOML file
+    /// mapping guidelines such as `"Rule 15.5"` to `mandatory`, `required`, `advisory` or
+    /// `disapplied`. Disapplied guidelines are not checked.
+    #[serde(default)]
+    pub misra_grp: Option<PathBuf>,
+    /// MISRA deviation records, relative to the repository root: a TOML file of
+    /// `[[deviation]]` tables with the `rule` deviated from, its scope (`file` and `function`
+    /// globs, `lines`), `rationale`, `approver` and `expires` date. Covered violations are
+    /// reported as deviated, and expired deviations fail the analysis. Mandatory guidelines,
+    /// including those the `misra_grp` makes mandatory, cannot be deviated.
+    #[serde(default)]
+    pub misra_deviations: Option<PathBuf>,
+    /// The least strict MISRA classification whose violations fail the analysis, after
+    /// re-categorization: `mandatory`, `required` or `advisory`. Violations of less strict
+    /// rules are still reported. Defaults to `advisory`, every violation not covered by a
+    /// deviation fails the analysis. Findings of other checks always fail it.
+    #[serde(default = "advisory")]
+    pub misra_fail_on: Classification,
+    /// Report findings of cppcheck checks without an issue code of their own, such as
+    /// `uninitvar`, under a catch-all issue code instead of only counting them.
+    #[serde(default)]
+    pub report_unmapped: bool,
+    /// Whether problems in `analysis_config.json` fail the run.
+    // read by `validation::validate` ahead of deserializing
+    #[allow(dead_code)]
+    #[serde(default)]
+    pub config_validation: ConfigValidation,
+}
+
+impl Default for AnalyzerMeta {
+    /// The serde defaults, used when no analysis config could be loaded.
+    fn default() -> Self {
+        serde_json::from_value(serde_json::json!({ "name": "", "enabled": true }))
+            .expect("every other field has a serde default")
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn advisory() -> Classification {
+    Classification::Advisory
+}
+
+fn max_file_size() -> u64 {
+    25_000_000
+}
+
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TestIssues {
+    /// Report them like any other issue.
+    #[default]
+    Report,
+    /// Report them with minor severity.
+    Downgrade,
+    /// Do not report them.
+    Skip,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
+pub struct Override {
+    /// Globs relative to the repository root, matched against files and their parent directories.
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub std: Option<String>,
+    #[serde(default)]
+    pub platform: Option<String>,
+    #[serde(default)]
+    pub language: Option<Language>,
+}
+
+/// Settings that require a separate cppcheck invocation when they differ.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Settings {
+    pub std: Option<String>,
+    pub platform: Option<String>,
+    pub language: Option<Language>,
+    /// The build files define a 64-bit `time_t`, the y2038 addon is not run.
+    pub time_bits_64: bool,
+}
+
+impl AnalyzerMeta {
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_file_size: self.max_file_size,
+            max_lines: self.max_lines,
+            skip_generated: self.skip_generated,
+            generated_markers: self.generated_markers.clone(),
+        }
+    }
+
+    /// The policy over the checks of the catalog and `custom`, those of the repository.
+    pub fn issue_policy(&self, custom: Vec<CustomEntry>) -> Result<IssuePolicy, globset::Error> {
+        IssuePolicy::new(
+            &self.disabled_issues,
+            &self.enabled_only,
+            &self.severity_overrides,
+            self.misra_version,
+            custom,
+        )
+    }
+
+    fn base_settings(&self) -> Settings {
+        Settings {
+            std: self.std.clone(),
+            platform: self.platform.clone(),
+            language: self.language,
+            time_bits_64: false,
+        }
+    }
+
+    /// Replaces the platform XML files and `forced_includes` with their canonical paths,
+    /// failing if one is outside of the repository.
+    pub fn resolve_repository_files(&mut self, code_path: &Path) -> Result<(), String> {
+        let platforms = std::iter::once(&mut self.platform)
+            .chain(self.overrides.iter_mut().map(|o| &mut o.platform))
+            .flatten()
+            .filter(|platform| platform.ends_with(".xml"));
+        for platform in platforms {
+            *platform = repository_file(code_path, Path::new(platform))
+                .map_err(|err| format!("platform {err}"))?
+                .display()
+                .to_string();
+        }
+        for include in &mut self.forced_includes {
+            *include = repository_file(code_path, include)
+                .map_err(|err| format!("forced include {err}"))?;
+        }
+        Ok(())
+    }
+
+    /// Resolves the effective settings for every file, honouring `overrides`.
+    pub fn settings_for<'a>(
+        &self,
+        code_path: &Path,
+        files: impl IntoIterator<Item = &'a PathBuf>,
+    ) -> Result<Vec<(Settings, &'a PathBuf)>, globset::Error> {
+        let overrides = self
+            .overrides
+            .iter()
+            .map(|o| glob_set(&o.paths).map(|set| (set, o)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let base = self.base_settings();
+        Ok(files
+            .into_iter()
+            .map(|file| {
+                let mut settings = base.clone();
+                let relative = file.strip_prefix(code_path).unwrap_or(file);
+                for (set, o) in &overrides {
+                    if !matches_path(set, relative) {
+                        continue;
+                    }
+                    if o.std.is_some() {
+                        settings.std = o.std.clone();
+                    }
+                    if o.platform.is_some() {
+                        settings.platform = o.platform.clone();
+                    }
+                    if o.language.is_some() {
+                        settings.language = o.language;
+                    }
+                }
+                (settings, file)
+            })
+            .collect())
+    }
+}
+
+/// Builds a [`GlobSet`] where `*` does not cross directory boundaries.
+pub(crate) fn glob_set<S: AsRef<str>>(patterns: &[S]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref().trim_start_matches("./");
+        builder.add(
+            GlobBuilder::new(pattern.trim_end_matches('/'))
+                .literal_separator(true)
+                .build()?,
+        );
+    }
+    builder.build()
+}
+
+/// Resolves `relative` below `code_path`, following symbolic links, and fails when it
+/// leads outside of the repository.
+pub(crate) fn repository_file(code_path: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let canonical = code_path
+        .join(relative)
+        .canonicalize()
+        .map_err(|err| format!("`{}`: {err}", relative.display()))?;
+    let root = code_path
+        .canonicalize()
+        .unwrap_or_else(|_| code_path.to_path_buf());
+    if !canonical.starts_with(&root) {
+        return Err(format!(
+            "`{}` is outside of the repository",
+            relative.display()
+        ));
+    }
+    Ok(canonical)
+}
+
+/// Checks a relative path, and every directory containing it, against `set`.
+pub(crate) fn matches_path(set: &GlobSet, relative: &Path) -> bool {
+    relative
+        .ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .any(|p| set.is_match(p))
+}
+
+#[test]
+fn t_overrides() {
+    let meta: AnalyzerMeta = serde_json::from_str(
+        r#"{
+            "name": "cxx",
+            "enabled": true,
+            "std": "c++17",
+            "platform": "unix64",
+            "overrides": [
+                { "paths": ["firmware"], "std": "c99", "platform": "msp430.xml", "language": "c" },
+                { "paths": ["firmware/boot/*.c"], "platform": "avr8" }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let files = [
+        PathBuf::from("/code/src/main.cpp"),
+        PathBuf::from("/code/firmware/drivers/uart.c"),
+        PathBuf::from("/code/firmware/boot/start.c"),
+    ];
+    let settings = meta.settings_for(Path::new("/code"), &files).unwrap();
+    let platforms: Vec<_> = settings
+        .iter()
+        .map(|(s, _)| (s.std.as_deref(), s.platform.as_deref(), s.language))
+        .collect();
+    assert_eq!(
+        platforms,
+        [
+            (Some("c++17"), Some("unix64"), None),
+            (Some("c99"), Some("msp430.xml"), Some(Language::C)),
+            (Some("c99"), Some("avr8"), Some(Language::C)),
+        ]
+    );
+
+    let default = AnalyzerMeta::default();
+    assert!(default.auto_libraries && default.skip_generated);
+    assert_eq!(default.addons, [Addon::Misra]);
+}
+
+#[test]
+fn t_repository_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let code_path = dir.path().join("code");
+    std::fs::create_dir_all(code_path.join("platforms")).unwrap();
+    std::fs::write(code_path.join("platforms/msp430.xml"), "<platform/>").unwrap();
+    std::fs::write(code_path.join("config.h"), "").unwrap();
+    std::fs::write(dir.path().join("secret.h"), "").unwrap();
+    let meta = |config: serde_json::Value| -> AnalyzerMeta {
+        let mut config = config;
+        config["name"] = "cxx".into();
+        config["enabled"] = true.into();
+        serde_json::from_value(config).unwrap()
+    };
+
+    let mut resolved = meta(serde_json::json!({
+        "platform": "unix64",
+        "overrides": [{ "paths": ["firmware"], "platform": "platforms/msp430.xml" }],
+        "forced_includes": ["config.h"],
+    }));
+    resolved.resolve_repository_files(&code_path).unwrap();
+    let root = code_path.canonicalize().unwrap();
+    assert_eq!(resolved.platform.as_deref(), Some("unix64"));
+    assert_eq!(
+        resolved.overrides[0].platform,
+        Some(root.join("platforms/msp430.xml").display().to_string())
+    );
+    assert_eq!(resolved.forced_includes, [root.join("config.h")]);
+
+    let err = meta(serde_json::json!({ "forced_includes": ["../secret.h"] }))
+        .resolve_repository_files(&code_path)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        "forced include `../secret.h` is outside of the repository"
+    );
+    std::fs::write(dir.path().join("evil.xml"), "<platform/>").unwrap();
+    let evil = dir.path().join("evil.xml").display().to_string();
+    let err = meta(serde_json::json!({ "platform": evil }))
+        .resolve_repository_files(&code_path)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        format!("platform `{evil}` is outside of the repository")
+    );
 }
@@ -0,0 +1,148 @@
+use std::{collections::BTreeSet, error::Error, path::PathBuf};
+
+use crate::{addon::Addon, catalog, cppcheck};
+
+const USAGE: &str = "usage: cppcheck-deepsource check-coverage [--errorlist FILE]";
+
+/// How the checks of a cppcheck version line up with the catalog.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Checks missing from the catalog.
+    pub unmapped: BTreeSet<String>,
+    /// Catalog entries cppcheck no longer has.
+    pub removed: BTreeSet<String>,
+    /// Removed entries paired with the unmapped check they were most likely renamed to.
+    pub renamed: Vec<(String, String)>,
+}
+
+impl Coverage {
+    pub fn is_complete(&self) -> bool {
+        self.unmapped.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+/// Compares the ids of `cppcheck --errorlist` with the catalog. Addon checks are not
+/// part of the error list and are left out.
+pub fn diff<'a>(checks: impl IntoIterator<Item = &'a str>) -> Coverage {
+    let checks: BTreeSet<_> = checks.into_iter().collect();
+    let mut unmapped: BTreeSet<_> = checks
+        .iter()
+        .filter(|id| catalog::lookup(id).is_none())
+        .map(|id| id.to_string())
+        .collect();
+    let mut removed: BTreeSet<_> = catalog::entries()
+        .iter()
+        .filter(|e| Addon::of(e.id).is_none() && !checks.contains(e.id))
+        .map(|e| e.id.to_string())
+        .collect();
+
+    let mut renamed = vec![];
+    for old in removed.clone() {
+        let new = unmapped
+            .iter()
+            .map(|new| (distance(&old.to_lowercase(), &new.to_lowercase()), new))
+            .filter(|(d, _)| *d <= 2)
+            .min()
+            .map(|(_, new)| new.clone());
+        if let Some(new) = new {
+            removed.remove(&old);
+            unmapped.remove(&new);
+            renamed.push((old, new));
+        }
+    }
+    Coverage {
+        unmapped,
+        removed,
+        renamed,
+    }
+}
+
+/// Levenshtein distance.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut row: Vec<_> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            let next = (row[j + 1] + 1).min(row[j] + 1).min(prev + cost);
+            prev = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// Reports the checks of the installed cppcheck, or of `--errorlist FILE`, missing from
+/// the catalog, and fails unless the catalog covers them exactly.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let path = match args {
+        [] => None,
+        [flag, path] if flag == "--errorlist" => Some(PathBuf::from(path)),
+        _ => return Err(USAGE.into()),
+    };
+    let errorlist = cppcheck::errorlist(path.as_deref())?;
+    let coverage = diff(errorlist.errors.error.iter().map(|e| e.id.as_str()));
+    for id in &coverage.unmapped {
+        log::error!("`{id}` is not in the catalog");
+    }
+    for id in &coverage.removed {
+        log::error!("`{id}` is no longer reported by cppcheck");
+    }
+    for (old, new) in &coverage.renamed {
+        log::error!("`{old}` was likely renamed to `{new}`");
+    }
+    if !coverage.is_complete() {
+        return Err("the catalog is out of sync with cppcheck, see above".into());
+    }
+    Ok(())
+}
+
+/// `cppcheck --errorlist` of the cppcheck installed by the Dockerfile.
+#[cfg(test)]
+const ERRORLIST: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/catalog/errorlist.xml");
+
+/// Checks of the error list deliberately left out of the catalog.
+#[cfg(test)]
+const ACCEPTED_UNMAPPED: &[&str] = &[];
+
+/// Catalog entries deliberately kept although the error list does not have them.
+#[cfg(test)]
+const ACCEPTED_REMOVED: &[&str] = &[];
+
+#[test]
+#[ignore = "needs catalog/errorlist.xml, the `cppcheck --errorlist` of the Docker image"]
+fn t_coverage() {
+    let errorlist = cppcheck::errorlist(Some(ERRORLIST.as_ref())).unwrap();
+    let coverage = diff(errorlist.errors.error.iter().map(|e| e.id.as_str()));
+    assert_eq!(
+        coverage,
+        Coverage {
+            unmapped: ACCEPTED_UNMAPPED.iter().map(|id| id.to_string()).collect(),
+            removed: ACCEPTED_REMOVED.iter().map(|id| id.to_string()).collect(),
+            renamed: vec![],
+        }
+    );
+}
+
+#[test]
+fn t_diff() {
+    let checks = catalog::entries()
+        .iter()
+        .filter(|e| Addon::of(e.id).is_none())
+        .map(|e| e.id)
+        .filter(|id| !["cstyleCast", "nullPointerDefaultArg"].contains(id));
+    assert_eq!(diff(checks.clone()).removed.len(), 2);
+
+    let coverage = diff(checks.chain(["nullPointerDefaultArgs", "brandNewCheck"]));
+    assert_eq!(coverage.unmapped, ["brandNewCheck".to_string()].into());
+    assert_eq!(coverage.removed, ["cstyleCast".to_string()].into());
+    assert_eq!(
+        coverage.renamed,
+        [(
+            "nullPointerDefaultArg".to_string(),
+            "nullPointerDefaultArgs".to_string()
+        )]
+    );
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use globset::GlobSet;
+use serde::Deserialize;
+
+use crate::config::{glob_set, matches_path};
+
+/// The parts of a repository's `.deepsource.toml` relevant to this analyzer.
+#[derive(Deserialize, Default, Debug)]
+pub struct DeepSourceConfig {
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    #[serde(default)]
+    pub test_patterns: Vec<String>,
+}
+
+impl DeepSourceConfig {
+    /// Reads `.deepsource.toml` from the repository root, if present.
+    pub fn load(code_path: &Path) -> Self {
+        let path = code_path.join(".deepsource.toml");
+        let Ok(src) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        toml::from_str(&src).unwrap_or_else(|err| {
+            log::error!("Failed to parse `{}`: {err}", path.display());
+            Self::default()
+        })
+    }
+
+    pub fn patterns(&self) -> Result<Patterns, globset::Error> {
+        Ok(Patterns {
+            exclude: glob_set(&self.exclude_patterns)?,
+            test: glob_set(&self.test_patterns)?,
+        })
+    }
+}
+
+/// Compiled `exclude_patterns` and `test_patterns`, matched relative to the repository root.
+pub struct Patterns {
+    exclude: GlobSet,
+    test: GlobSet,
+}
+
+impl Patterns {
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        matches_path(&self.exclude, relative)
+    }
+
+    pub fn is_test(&self, relative: &Path) -> bool {
+        matches_path(&self.test, relative)
+    }
+}
+
+#[test]
+fn t_patterns() {
+    let config: DeepSourceConfig = toml::from_str(include_str!("../.deepsource.toml")).unwrap();
+    let patterns = config.patterns().unwrap();
+    assert!(patterns.is_excluded(Path::new("tests/checks/a.c")));
+    assert!(patterns.is_excluded(Path::new("lib/tests/a.c")));
+    assert!(!patterns.is_excluded(Path::new("src/main.c")));
+    assert!(patterns.is_test(Path::new("src/parser_tests.rs")));
+    assert!(!patterns.is_test(Path::new("src/parser.rs")));
+}
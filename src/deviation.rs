@@ -0,0 +1,422 @@
+//! Deviation records: documented, approved violations of MISRA guidelines.
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt,
+    ops::RangeInclusive,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use globset::{Glob, GlobMatcher, GlobSet};
+use serde::Deserialize;
+
+use crate::{catalog::Classification, config, misra};
+
+/// A deviation file, a TOML file of `[[deviation]]` records.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct File {
+    #[serde(default)]
+    deviation: Vec<Record>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Record {
+    /// Reference of the record in the project's deviation register.
+    id: Option<String>,
+    /// The guideline deviated from, as in the re-categorization plan.
+    rule: String,
+    /// Glob over the files, relative to the repository root, or their directories.
+    #[serde(default = "any_file")]
+    file: String,
+    /// Glob over the name of the function containing the violation.
+    function: Option<String>,
+    /// A line, `42`, or a range of lines, `40-60`.
+    lines: Option<String>,
+    rationale: String,
+    approver: String,
+    /// The last day the deviation applies, `YYYY-MM-DD`.
+    expires: Option<String>,
+}
+
+fn any_file() -> String {
+    "**".to_string()
+}
+
+/// A calendar date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: i64,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    fn parse(src: &str) -> Option<Self> {
+        let mut parts = src.trim().splitn(3, '-');
+        let date = Date {
+            year: parts.next()?.parse().ok()?,
+            month: parts.next()?.parse().ok()?,
+            day: parts.next()?.parse().ok()?,
+        };
+        ((1..=12).contains(&date.month) && (1..=date.days_in_month()).contains(&date.day))
+            .then_some(date)
+    }
+
+    fn days_in_month(&self) -> u32 {
+        match self.month {
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    /// The current date in UTC.
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self::from_days(i64::try_from(secs / 86_400).unwrap_or_default())
+    }
+
+    /// The date `days` after 1970-01-01.
+    fn from_days(days: i64) -> Self {
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        Date {
+            year: yoe + era * 400 + i64::from(month <= 2),
+            month,
+            day,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A deviation record, ready to be matched against violations.
+#[derive(Debug)]
+pub struct Deviation {
+    /// The record id, or the guideline and file glob when it has none.
+    pub label: String,
+    /// cppcheck id of the guideline.
+    id: &'static str,
+    file: GlobSet,
+    function: Option<GlobMatcher>,
+    lines: Option<RangeInclusive<u32>>,
+    pub expires: Option<Date>,
+    /// Violations covered by the deviation.
+    matched: usize,
+}
+
+impl Deviation {
+    fn new(record: Record, grp: &misra::Grp) -> Result<Self, String> {
+        let entry = misra::lookup(&record.rule).ok_or_else(|| {
+            format!(
+                "deviation {}: `{}` is not a known MISRA C guideline",
+                record.id.as_deref().unwrap_or(&record.rule),
+                record.rule
+            )
+        })?;
+        let label = record
+            .id
+            .clone()
+            .unwrap_or_else(|| format!("{} in `{}`", misra::guideline(entry.id), record.file));
+        let invalid = |what: &str| format!("deviation {label}: {what}");
+        // MISRA Compliance:2020 does not permit deviating mandatory guidelines
+        if grp.classification(entry) == Some(Classification::Mandatory) {
+            return Err(invalid(&format!(
+                "{} is mandatory in the project, it cannot be deviated",
+                misra::guideline(entry.id)
+            )));
+        }
+        if record.rationale.trim().is_empty() {
+            return Err(invalid("the rationale is empty"));
+        }
+        if record.approver.trim().is_empty() {
+            return Err(invalid("the approver is empty"));
+        }
+        let lines = match &record.lines {
+            Some(lines) => {
+                let (first, last) = lines.split_once('-').unwrap_or((lines, lines));
+                match (first.trim().parse(), last.trim().parse()) {
+                    (Ok(first), Ok(last)) if first <= last => Some(first..=last),
+                    _ => return Err(invalid(&format!("`{lines}` is not a line range"))),
+                }
+            }
+            None => None,
+        };
+        let expires = match &record.expires {
+            Some(expires) => Some(
+                Date::parse(expires)
+                    .ok_or_else(|| invalid(&format!("`{expires}` is not a YYYY-MM-DD date")))?,
+            ),
+            None => None,
+        };
+        Ok(Deviation {
+            id: entry.id,
+            file: config::glob_set(&[&record.file]).map_err(|err| invalid(&err.to_string()))?,
+            function: match &record.function {
+                Some(function) => Some(
+                    Glob::new(function)
+                        .map_err(|err| invalid(&err.to_string()))?
+                        .compile_matcher(),
+                ),
+                None => None,
+            },
+            lines,
+            expires,
+            matched: 0,
+            label,
+        })
+    }
+}
+
+/// The deviations of a project.
+#[derive(Debug, Default)]
+pub struct Deviations {
+    deviations: Vec<Deviation>,
+}
+
+impl Deviations {
+    /// Loads the records at `path`, classifying their guidelines as `grp` does.
+    pub fn load(path: &Path, grp: &misra::Grp) -> Result<Self, Box<dyn Error>> {
+        let src = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read `{}`: {err}", path.display()))?;
+        Self::parse(&src, grp).map_err(|err| format!("{}: {err}", path.display()).into())
+    }
+
+    fn parse(src: &str, grp: &misra::Grp) -> Result<Self, String> {
+        let file: File = toml::from_str(src).map_err(|err| err.to_string())?;
+        Ok(Deviations {
+            deviations: file
+                .deviation
+                .into_iter()
+                .map(|record| Deviation::new(record, grp))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Deviations which expired before `today`.
+    pub fn expired(&self, today: Date) -> impl Iterator<Item = &Deviation> {
+        self.deviations
+            .iter()
+            .filter(move |d| d.expires.is_some_and(|expires| expires < today))
+    }
+
+    /// Finds the deviation covering a violation of the guideline `id` at `line` of
+    /// `relative`, returning its label. `function` gives the name of the function
+    /// containing the violation, and is only called for deviations scoped to a function.
+    pub fn apply(
+        &mut self,
+        id: &str,
+        relative: &Path,
+        line: u32,
+        function: impl Fn() -> Option<String>,
+    ) -> Option<String> {
+        let deviation = self.deviations.iter_mut().find(|d| {
+            d.id == id
+                && config::matches_path(&d.file, relative)
+                && d.lines.as_ref().is_none_or(|lines| lines.contains(&line))
+                && d.function
+                    .as_ref()
+                    .is_none_or(|glob| function().is_some_and(|name| glob.is_match(name)))
+        })?;
+        deviation.matched += 1;
+        Some(deviation.label.clone())
+    }
+
+    /// Labels of the deviations which covered no violation.
+    pub fn unused(&self) -> impl Iterator<Item = &str> {
+        self.deviations
+            .iter()
+            .filter(|d| d.matched == 0)
+            .map(|d| d.label.as_str())
+    }
+
+    /// Deviated violations by cppcheck id.
+    pub fn counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for deviation in &self.deviations {
+            *counts.entry(deviation.id).or_default() += deviation.matched;
+        }
+        counts
+    }
+}
+
+/// The name of the function whose body contains `line`, found by following braces,
+/// which is good enough for the usual layout of C code.
+pub fn enclosing_function(src: &str, line: u32) -> Option<String> {
+    let mut depth = 0usize;
+    let mut current = None;
+    // declarator text since the last top level declaration
+    let mut header = String::new();
+    for (n, text) in src.lines().take(line as usize).enumerate() {
+        let last = n + 1 == line as usize;
+        if last && depth > 0 {
+            return current;
+        }
+        let text = text.split("//").next().unwrap_or_default();
+        if text.trim_start().starts_with('#') {
+            continue;
+        }
+        for c in text.chars() {
+            match c {
+                '{' => {
+                    if depth == 0 {
+                        current = function_name(&header);
+                    }
+                    depth += 1;
+                    // a function on a single line
+                    if last && current.is_some() {
+                        return current;
+                    }
+                }
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        current = None;
+                        header.clear();
+                    }
+                }
+                ';' if depth == 0 => header.clear(),
+                _ if depth == 0 => header.push(c),
+                _ => {}
+            }
+        }
+        header.push(' ');
+    }
+    None
+}
+
+/// The name declared by a function declarator such as `static int main(void)`.
+fn function_name(header: &str) -> Option<String> {
+    let declarator = header[..header.find('(')?].trim_end();
+    let start = declarator
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':' || c == '~'))
+        .map_or(0, |i| i + 1);
+    let name = &declarator[start..];
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[test]
+fn t_deviations() {
+    let grp = misra::Grp::default();
+    let mut deviations = Deviations::parse(
+        r#"
+        [[deviation]]
+        id = "DEV-001"
+        rule = "Rule 11.5"
+        file = "src/hal"
+        function = "hal_*"
+        rationale = "Register access"
+        approver = "J. Doe"
+        expires = "2030-01-31"
+
+        [[deviation]]
+        rule = "21.3"
+        file = "src/*.c"
+        lines = "10-20"
+        rationale = "Allocation at start-up only"
+        approver = "J. Doe"
+        expires = "2020-06-01"
+        "#,
+        &grp,
+    )
+    .unwrap();
+    assert_eq!(
+        deviations
+            .expired(Date::parse("2024-01-01").unwrap())
+            .map(|d| d.label.as_str())
+            .collect::<Vec<_>>(),
+        ["Rule 21.3 in `src/*.c`"]
+    );
+
+    let hal = || Some("hal_write".to_string());
+    assert_eq!(
+        deviations
+            .apply("misra-c2012-11.5", "src/hal/io.c".as_ref(), 3, hal)
+            .as_deref(),
+        Some("DEV-001")
+    );
+    assert_eq!(
+        deviations.apply("misra-c2012-11.5", "src/hal/io.c".as_ref(), 3, || None),
+        None
+    );
+    assert_eq!(
+        deviations.apply("misra-c2012-21.3", "src/hal/io.c".as_ref(), 12, || None),
+        None
+    );
+    assert_eq!(
+        deviations.apply("misra-c2012-21.3", "src/a.c".as_ref(), 21, || None),
+        None
+    );
+    assert_eq!(deviations.counts()["misra-c2012-11.5"], 1);
+    assert_eq!(
+        deviations.unused().collect::<Vec<_>>(),
+        ["Rule 21.3 in `src/*.c`"]
+    );
+
+    let err = Deviations::parse(
+        "[[deviation]]\nrule = \"99.1\"\nrationale = \"\"\napprover = \"\"\n",
+        &grp,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        "deviation 99.1: `99.1` is not a known MISRA C guideline"
+    );
+
+    for date in [
+        "2026-02-31",
+        "2025-04-31",
+        "2023-02-29",
+        "2100-02-29",
+        "2024-13-01",
+    ] {
+        assert_eq!(Date::parse(date), None, "{date}");
+    }
+    assert!(Date::parse("2024-02-29").is_some() && Date::parse("2000-02-29").is_some());
+    let err = Deviations::parse(
+        "[[deviation]]\nid = \"DEV-2\"\nrule = \"2.3\"\nrationale = \"r\"\napprover = \"a\"\nexpires = \"2026-02-31\"\n",
+        &grp,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        "deviation DEV-2: `2026-02-31` is not a YYYY-MM-DD date"
+    );
+
+    let record = |rule: &str| {
+        format!("[[deviation]]\nid = \"DEV-3\"\nrule = \"{rule}\"\nrationale = \"r\"\napprover = \"a\"\n")
+    };
+    assert_eq!(
+        Deviations::parse(&record("Rule 17.3"), &grp).unwrap_err(),
+        "deviation DEV-3: Rule 17.3 is mandatory in the project, it cannot be deviated"
+    );
+    let (grp, problems) = misra::Grp::parse("\"Rule 11.5\" = \"mandatory\"").unwrap();
+    assert!(problems.is_empty());
+    assert!(Deviations::parse(&record("Rule 11.5"), &grp).is_err());
+    assert!(Deviations::parse(&record("Rule 11.5"), &misra::Grp::default()).is_ok());
+
+    assert_eq!(Date::from_days(0).to_string(), "1970-01-01");
+    assert_eq!(Date::from_days(19_782).to_string(), "2024-02-29");
+
+    let src = "#include <x.h>\nstatic int count;\n\nvoid hal_write(int *p)\n{\n    if (p) {\n        *p = 1;\n    }\n}\nint main(void) { return 0; }\n";
+    assert_eq!(enclosing_function(src, 7).as_deref(), Some("hal_write"));
+    assert_eq!(enclosing_function(src, 10).as_deref(), Some("main"));
+    assert_eq!(enclosing_function(src, 2), None);
+}
@@ -18,7 +18,7 @@ impl Default for Logger {
 
 impl Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        &metadata.level().to_level_filter() <= &self.level
+        metadata.level().to_level_filter() <= self.level
     }
 
     fn log(&self, record: &Record) {
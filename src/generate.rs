@@ -0,0 +1,282 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Write,
+    path::PathBuf,
+};
+
+use serde::Serialize;
+
+use crate::{
+    catalog::{self, Category, Entry},
+    cppcheck,
+    result::Severity,
+};
+
+const USAGE: &str =
+    "usage: cppcheck-deepsource generate-issues [--check] [--errorlist FILE] [--rule-texts FILE] DIR";
+
+/// Options of the `generate-issues` subcommand.
+#[derive(Debug, Default)]
+struct Options {
+    /// Compare with the files in `dir` instead of writing them.
+    check: bool,
+    /// Output of `cppcheck --errorlist`, cppcheck is run when not given.
+    errorlist: Option<PathBuf>,
+    /// MISRA rule texts, in the format accepted by the addon's `--rule-texts`.
+    rule_texts: Option<PathBuf>,
+    dir: PathBuf,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut dir = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().map(PathBuf::from).ok_or(USAGE);
+            match arg.as_str() {
+                "--check" => options.check = true,
+                "--errorlist" => options.errorlist = Some(value()?),
+                "--rule-texts" => options.rule_texts = Some(value()?),
+                _ if arg.starts_with('-') || dir.is_some() => return Err(USAGE.to_string()),
+                _ => dir = Some(PathBuf::from(arg)),
+            }
+        }
+        options.dir = dir.ok_or(USAGE)?;
+        Ok(options)
+    }
+}
+
+/// A DeepSource issue definition.
+#[derive(Serialize, Debug, PartialEq)]
+struct Issue<'a> {
+    title: String,
+    verbose_name: &'a str,
+    category: Category,
+    severity: Severity,
+    description: String,
+}
+
+/// Writes one `<code>.toml` issue definition per catalog entry to `dir`, or with
+/// `--check`, fails if the files in `dir` differ from what would be written.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let options = Options::parse(args)?;
+    let errorlist = cppcheck::errorlist(options.errorlist.as_deref())?;
+    let checks: HashMap<_, _> = errorlist
+        .errors
+        .error
+        .iter()
+        .map(|e| (e.id.as_str(), e))
+        .collect();
+    let headlines = match &options.rule_texts {
+        Some(path) => headlines(&std::fs::read_to_string(path)?),
+        None => BTreeMap::new(),
+    };
+
+    let mut stale = vec![];
+    for entry in catalog::entries().iter().chain([&catalog::UNMAPPED]) {
+        let path = options.dir.join(format!("{}.toml", entry.code));
+        let issue = render(entry, checks.get(entry.id).copied(), &headlines);
+        let src = toml::to_string(&issue)?;
+        if !options.check {
+            std::fs::create_dir_all(&options.dir)?;
+            std::fs::write(&path, src)?;
+        } else if std::fs::read_to_string(&path).ok().as_deref() != Some(&src) {
+            stale.push(path);
+        }
+    }
+    if !options.check {
+        return Ok(());
+    }
+    // definitions of codes which were removed from the catalog
+    for file in std::fs::read_dir(&options.dir)? {
+        let path = file?.path();
+        let code = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        if path.extension().is_some_and(|e| e == "toml") && catalog::by_code(code).is_none() {
+            stale.push(path);
+        }
+    }
+    for path in &stale {
+        log::error!("`{}` is out of date", path.display());
+    }
+    if !stale.is_empty() {
+        return Err(format!(
+            "{} issue definition(s) in `{}` out of date, run again without `--check`",
+            stale.len(),
+            options.dir.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Reads the rule headlines out of MISRA rule texts, keyed like the suffix of
+/// the addon's ids, `2.3` for rules and `Dir-4.1` for directives.
+fn headlines(src: &str) -> BTreeMap<String, String> {
+    let mut headlines = BTreeMap::new();
+    let mut current = None;
+    for line in src.lines().map(str::trim) {
+        let mut words = line.split_whitespace();
+        let key = match (words.next(), words.next()) {
+            (Some("Rule"), Some(number)) => Some(number.to_string()),
+            (Some("Dir"), Some(number)) => Some(format!("Dir-{number}")),
+            _ => None,
+        };
+        match key {
+            Some(key) if key.contains('.') => current = Some(key),
+            _ if line.is_empty() => {}
+            _ => {
+                if let Some(key) = current.take() {
+                    headlines.insert(key, line.to_string());
+                }
+            }
+        }
+    }
+    headlines
+}
+
+fn render<'a>(
+    entry: &'a Entry,
+    check: Option<&cppcheck::Error>,
+    headlines: &BTreeMap<String, String>,
+) -> Issue<'a> {
+    let rule = entry.id.strip_prefix("misra-c2012-");
+    let headline = rule.and_then(|rule| headlines.get(rule));
+    let title = match headline {
+        Some(headline) => headline.trim_end_matches('.').to_string(),
+        None => entry.title.to_string(),
+    };
+
+    let mut description = String::new();
+    if let Some(text) = entry.description {
+        writeln!(description, "{text}\n").unwrap();
+    }
+    if let Some(rule) = rule {
+        let kind = if rule.starts_with("Dir-") {
+            "Directive"
+        } else {
+            "Rule"
+        };
+        let number = rule.trim_start_matches("Dir-");
+        let version = entry.misra_since.unwrap_or_default().year();
+        write!(
+            description,
+            "Violation of MISRA C:{version} {kind} {number}"
+        )
+        .unwrap();
+        match entry.misra {
+            Some(classification) => writeln!(description, " ({classification:?}).").unwrap(),
+            None => writeln!(description, ".").unwrap(),
+        }
+        if let Some(headline) = headline {
+            writeln!(description, "\n> {headline}").unwrap();
+        }
+    } else if let Some(cert) = entry.cert {
+        writeln!(
+            description,
+            "Violation of SEI CERT C {cert}: {}.",
+            entry.title
+        )
+        .unwrap();
+    } else if let Some(check) = check {
+        writeln!(description, "{}", check.verbose).unwrap();
+    }
+    let cwe = entry.cwe.or_else(|| check?.cwe.as_ref()?.parse().ok());
+    if let Some(cwe) = cwe {
+        writeln!(
+            description,
+            "\nSee [CWE-{cwe}](https://cwe.mitre.org/data/definitions/{cwe}.html)."
+        )
+        .unwrap();
+    }
+
+    Issue {
+        title,
+        verbose_name: entry.id,
+        category: entry.category,
+        severity: entry.severity.unwrap_or_else(|| {
+            check.map_or(Severity::Minor, |c| Severity::from_cppcheck(&c.severity))
+        }),
+        description: description.trim_end().to_string(),
+    }
+}
+
+#[test]
+fn t_render() {
+    let errorlist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<results version="2">
+    <cppcheck version="2.10.3"/>
+    <errors>
+        <error id="nullPointer" severity="error" msg="Null pointer dereference" verbose="Null pointer dereference" cwe="476"/>
+    </errors>
+</results>"#;
+    let errorlist: cppcheck::Results = quick_xml::de::from_str(errorlist).unwrap();
+    let headlines = headlines(
+        "Appendix A Summary of guidelines\nRule 2.3 Advisory\nA project should not contain unused type declarations\nRule 2.4\n\nText of rule 2.4\n",
+    );
+    assert_eq!(headlines.len(), 2);
+
+    let issue = render(
+        catalog::lookup("nullPointer").unwrap(),
+        errorlist.errors.error.first(),
+        &headlines,
+    );
+    assert_eq!(issue.severity, Severity::Critical);
+    assert_eq!(
+        issue.description,
+        "Null pointer dereference\n\nSee [CWE-476](https://cwe.mitre.org/data/definitions/476.html)."
+    );
+
+    let issue = render(catalog::lookup("cert-MSC30-c").unwrap(), None, &headlines);
+    assert_eq!(
+        issue.description,
+        "Violation of SEI CERT C MSC30-C: Do not use the rand() function for generating pseudorandom numbers.\n\nSee [CWE-338](https://cwe.mitre.org/data/definitions/338.html)."
+    );
+
+    let issue = render(
+        catalog::lookup("misra-c2012-2.3").unwrap(),
+        None,
+        &headlines,
+    );
+    assert_eq!(
+        issue.title,
+        "A project should not contain unused type declarations"
+    );
+    assert_eq!(
+        issue.description,
+        "Violation of MISRA C:2012 Rule 2.3 (Advisory).\n\n> A project should not contain unused type declarations"
+    );
+}
+
+#[test]
+fn t_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let errorlist = dir.path().join("errorlist.xml");
+    std::fs::write(
+        &errorlist,
+        r#"<results version="2"><errors><error id="nullPointer" severity="error" msg="Null pointer dereference" verbose="Null pointer dereference" cwe="476"/></errors></results>"#,
+    )
+    .unwrap();
+    let out = dir.path().join("issues");
+    let args = |check: bool| {
+        let mut args = vec!["--errorlist".to_string(), errorlist.display().to_string()];
+        if check {
+            args.push("--check".to_string());
+        }
+        args.push(out.display().to_string());
+        args
+    };
+    run(&args(false)).unwrap();
+    run(&args(true)).unwrap();
+
+    // an edited, a removed and an obsolete definition
+    std::fs::write(out.join("CXX-W3651.toml"), "title = \"edited\"\n").unwrap();
+    std::fs::remove_file(out.join("CXX-W3001.toml")).unwrap();
+    std::fs::write(out.join("CXX-W9999.toml"), "").unwrap();
+    let err = run(&args(true)).unwrap_err();
+    assert!(err.to_string().starts_with("3 issue definition(s) in "));
+}
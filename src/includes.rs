@@ -0,0 +1,305 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+use walkdir::WalkDir;
+
+/// A single `#include` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    pub name: String,
+    /// `<header>` rather than `"header"`
+    pub system: bool,
+}
+
+/// Extracts the `#include` directives of a source file.
+pub fn directives(src: &str) -> Vec<Include> {
+    src.lines()
+        .filter_map(|line| {
+            let line = line.trim_start().strip_prefix('#')?;
+            let line = line.trim_start().strip_prefix("include")?.trim_start();
+            let (close, system) = match line.chars().next()? {
+                '"' => ('"', false),
+                '<' => ('>', true),
+                _ => return None,
+            };
+            let name = &line[1..];
+            let name = &name[..name.find(close)?];
+            Some(Include {
+                name: name.to_string(),
+                system,
+            })
+        })
+        .collect()
+}
+
+/// Reads the `#include` directives of every file, skipping unreadable ones.
+pub fn scan<'a>(
+    files: impl IntoIterator<Item = &'a PathBuf>,
+) -> impl Iterator<Item = (&'a PathBuf, Vec<Include>)> {
+    files.into_iter().filter_map(|file| {
+        // sources are not guaranteed to be utf-8
+        let src = std::fs::read(file).ok()?;
+        Some((file, directives(&String::from_utf8_lossy(&src))))
+    })
+}
+
+/// Outcome of resolving the includes of the analyzed files.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// Include directories passed to cppcheck, configured ones first.
+    pub include_dirs: Vec<PathBuf>,
+    /// Quoted includes that could not be found, with the files including them.
+    pub unresolved: BTreeMap<String, BTreeSet<PathBuf>>,
+    /// Files included by each of the analyzed files.
+    includes: HashMap<PathBuf, BTreeSet<PathBuf>>,
+    /// `<header>` includes not found in the repository, provided by the system.
+    pub external: BTreeSet<String>,
+}
+
+impl Resolution {
+    /// Files included by the translation units `units`, directly or through other headers.
+    pub fn included_from<'a>(
+        &self,
+        units: impl IntoIterator<Item = &'a PathBuf>,
+    ) -> HashSet<PathBuf> {
+        let mut included = HashSet::new();
+        let mut pending: Vec<_> = units
+            .into_iter()
+            .filter_map(|unit| self.includes.get(&normalize(unit)))
+            .flatten()
+            .collect();
+        while let Some(file) = pending.pop() {
+            if included.insert(file.clone()) {
+                pending.extend(self.includes.get(file).into_iter().flatten());
+            }
+        }
+        included
+    }
+}
+
+/// Headers of the repository, indexed by file name.
+struct HeaderIndex(HashMap<String, Vec<PathBuf>>);
+
+impl HeaderIndex {
+    fn build(code_path: &Path) -> (Self, Vec<PathBuf>) {
+        let mut headers = HashMap::<String, Vec<PathBuf>>::new();
+        let mut include_dirs = vec![];
+        for entry in WalkDir::new(code_path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.file_name() != ".git")
+            .filter_map(Result::ok)
+        {
+            let name = entry.file_name().to_string_lossy();
+            if entry.file_type().is_dir() {
+                if name == "include" {
+                    include_dirs.push(entry.into_path());
+                }
+            } else if entry.file_type().is_file() {
+                headers
+                    .entry(name.into_owned())
+                    .or_default()
+                    .push(entry.into_path());
+            }
+        }
+        (HeaderIndex(headers), include_dirs)
+    }
+
+    /// Finds the directory `dir` such that `dir/name` exists in the repository.
+    fn locate(&self, name: &str) -> Option<PathBuf> {
+        let relative = Path::new(name);
+        let file_name = relative.file_name()?.to_str()?;
+        self.0.get(file_name)?.iter().find_map(|header| {
+            let mut dir = header.as_path();
+            for _ in relative.components() {
+                dir = dir.parent()?;
+            }
+            (dir.join(relative) == *header).then(|| dir.to_path_buf())
+        })
+    }
+}
+
+/// Resolves the scanned includes against `include_paths`, optionally
+/// discovering additional include directories in `code_path`.
+pub fn resolve(
+    code_path: &Path,
+    directives: &[(&PathBuf, Vec<Include>)],
+    include_paths: &[PathBuf],
+    discover: bool,
+) -> Resolution {
+    let mut include_dirs: Vec<PathBuf> = include_paths.iter().map(|p| code_path.join(p)).collect();
+    let index = discover.then(|| {
+        let (index, dirs) = HeaderIndex::build(code_path);
+        for dir in dirs {
+            if !include_dirs.contains(&dir) {
+                include_dirs.push(dir);
+            }
+        }
+        index
+    });
+
+    let mut unresolved = BTreeMap::<String, BTreeSet<PathBuf>>::new();
+    let mut includes = HashMap::<PathBuf, BTreeSet<PathBuf>>::new();
+    let mut external = BTreeSet::new();
+    for (file, directives) in directives {
+        let here = file.parent().unwrap_or(code_path);
+        let included = includes.entry(normalize(file)).or_default();
+        for include in directives {
+            if let Some(found) = std::iter::once(here)
+                .chain(include_dirs.iter().map(PathBuf::as_path))
+                .map(|d| d.join(&include.name))
+                .find(|f| f.is_file())
+            {
+                included.insert(normalize(&found));
+                continue;
+            }
+            if let Some(dir) = index.as_ref().and_then(|i| i.locate(&include.name)) {
+                included.insert(normalize(&dir.join(&include.name)));
+                include_dirs.push(dir);
+                continue;
+            }
+            // system headers are expected to live outside of the repository
+            if include.system {
+                external.insert(include.name.clone());
+            } else {
+                unresolved
+                    .entry(include.name.clone())
+                    .or_default()
+                    .insert(file.to_path_buf());
+            }
+        }
+    }
+    Resolution {
+        include_dirs,
+        unresolved,
+        includes,
+        external,
+    }
+}
+
+/// Lexically removes `.` and `..` components, without touching the file system.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[test]
+fn t_directives() {
+    let src = r#"
+        #include <stdio.h>
+        #  include "foo/bar.h" // trailing comment
+        #define include "nope.h"
+        #include MACRO_HEADER
+    "#;
+    assert_eq!(
+        directives(src),
+        [
+            Include {
+                name: "stdio.h".into(),
+                system: true
+            },
+            Include {
+                name: "foo/bar.h".into(),
+                system: false
+            },
+        ]
+    );
+}
+
+#[test]
+fn t_included_from() {
+    let dir = tempfile::tempdir().unwrap();
+    let code_path = dir.path();
+    for (name, src) in [
+        ("main.c", "#include \"c.h\"\n"),
+        ("c.h", "#include \"d.h\"\n"),
+        ("d.h", ""),
+        // included by each other only
+        ("a.h", "#include \"b.h\"\n"),
+        ("b.h", "#include \"a.h\"\n"),
+    ] {
+        std::fs::write(code_path.join(name), src).unwrap();
+    }
+    let files: Vec<_> = ["main.c", "c.h", "d.h", "a.h", "b.h"]
+        .map(|name| code_path.join(name))
+        .into();
+    let directives: Vec<_> = scan(&files).collect();
+    let resolution = resolve(code_path, &directives, &[], false);
+    let included = resolution.included_from(&files[..1]);
+    assert_eq!(
+        included,
+        [code_path.join("c.h"), code_path.join("d.h")].into()
+    );
+}
+
+#[test]
+fn t_resolve() {
+    let dir = tempfile::tempdir().unwrap();
+    let code_path = dir.path();
+    for (name, src) in [
+        (
+            "src/main.c",
+            "#include \"config.h\"\n#include \"a.h\"\n#include \"b.h\"\n\
+             #include \"proto/msg.h\"\n#include \"missing.h\"\n#include <stdio.h>\n",
+        ),
+        ("src/other.c", "#include \"missing.h\"\n#include <b.h>\n"),
+        ("cfg/config.h", ""),
+        ("libs/a/include/a.h", ""),
+        ("libs/b/include/b.h", ""),
+        ("gen/proto/msg.h", ""),
+    ] {
+        let path = code_path.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, src).unwrap();
+    }
+    let files = [code_path.join("src/main.c"), code_path.join("src/other.c")];
+    let directives: Vec<_> = scan(&files).collect();
+    let configured = [PathBuf::from("cfg"), PathBuf::from("libs/b/include")];
+
+    let resolution = resolve(code_path, &directives, &configured, true);
+    // configured first, then the `include` directories, then those found for a header
+    assert_eq!(
+        resolution.include_dirs,
+        ["cfg", "libs/b/include", "libs/a/include", "gen"].map(|d| code_path.join(d))
+    );
+    assert_eq!(
+        resolution.unresolved,
+        BTreeMap::from([("missing.h".to_string(), files.iter().cloned().collect())])
+    );
+    assert_eq!(resolution.external, ["stdio.h".to_string()].into());
+    assert_eq!(
+        resolution.included_from(&files),
+        [
+            "cfg/config.h",
+            "libs/a/include/a.h",
+            "libs/b/include/b.h",
+            "gen/proto/msg.h"
+        ]
+        .map(|f| code_path.join(f))
+        .into()
+    );
+
+    // without discovery, only the configured directories are searched
+    let resolution = resolve(code_path, &directives, &configured, false);
+    assert_eq!(
+        resolution.include_dirs,
+        ["cfg", "libs/b/include"].map(|d| code_path.join(d))
+    );
+    assert_eq!(
+        resolution.unresolved.keys().collect::<Vec<_>>(),
+        ["a.h", "missing.h", "proto/msg.h"]
+    );
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    addon::{Addon, Naming},
+    config::Settings,
+};
+
+/// A single cppcheck run over a group of files sharing the same [`Settings`].
+#[derive(Debug)]
+pub struct Invocation {
+    pub settings: Settings,
+    pub files: Vec<PathBuf>,
+}
+
+impl Invocation {
+    /// Groups files by their effective settings, one invocation per distinct group.
+    pub fn group<'a>(settings: impl IntoIterator<Item = (Settings, &'a PathBuf)>) -> Vec<Self> {
+        let mut groups = BTreeMap::<Settings, Vec<PathBuf>>::new();
+        for (settings, file) in settings {
+            groups.entry(settings).or_default().push(file.clone());
+        }
+        groups
+            .into_iter()
+            .map(|(settings, mut files)| {
+                files.sort();
+                Invocation { settings, files }
+            })
+            .collect()
+    }
+
+    /// Stable identifier for this group, used to keep the build cache of each group apart.
+    fn cache_key(&self) -> String {
+        let settings = &self.settings;
+        let key = [
+            settings.std.as_deref().unwrap_or_default(),
+            settings.platform.as_deref().unwrap_or_default(),
+            settings.language.map_or("", |l| l.as_cppcheck_arg()),
+            if settings.time_bits_64 { "time64" } else { "" },
+        ]
+        .join("\0");
+        format!("{:016x}", fnv1a(key.as_bytes()))
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the hashers of the standard library is fixed across releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// What a cppcheck run left behind.
+#[derive(Debug)]
+pub struct Report {
+    /// The XML report.
+    pub path: PathBuf,
+    /// cppcheck's standard error, where addon tracebacks end up.
+    pub stderr: String,
+}
+
+/// Options shared by every [`Invocation`] of a run.
+#[derive(Debug, Default)]
+pub struct Cppcheck {
+    pub executable: String,
+    /// Directory receiving file lists and XML reports.
+    pub work_dir: PathBuf,
+    pub cache_path: Option<PathBuf>,
+    pub defines: Vec<String>,
+    pub undefines: Vec<String>,
+    pub include_dirs: Vec<PathBuf>,
+    /// Headers of the repository, as resolved by [`AnalyzerMeta::resolve_repository_files`].
+    ///
+    /// [`AnalyzerMeta::resolve_repository_files`]: crate::config::AnalyzerMeta::resolve_repository_files
+    pub forced_includes: Vec<PathBuf>,
+    /// Library names or paths to `*.cfg` files.
+    pub libraries: Vec<String>,
+    /// cppcheck ids not to check for.
+    pub suppressions: Vec<String>,
+    /// Addons to run on the translation units they apply to.
+    pub addons: Vec<Addon>,
+    /// Addons of the repository, run on every translation unit.
+    pub custom_addons: Vec<PathBuf>,
+    /// Conventions to run the naming addon with.
+    pub naming: Option<Naming>,
+    /// Rule files of the repository.
+    pub rule_files: Vec<PathBuf>,
+    /// Rule file generated from the rules of the config.
+    pub inline_rules: Option<String>,
+}
+
+impl Cppcheck {
+    fn args(&self, invocation: &Invocation, file_list: &Path, output_path: &Path) -> Vec<String> {
+        let mut args = vec![
+            format!("--file-list={}", file_list.display()),
+            "-l".to_string(),
+            "6".to_string(),
+            "--xml".to_string(),
+            format!("--output-file={}", output_path.display()),
+        ];
+        let settings = &invocation.settings;
+        if let Some(std) = &settings.std {
+            args.push(format!("--std={std}"));
+        }
+        if let Some(platform) = &settings.platform {
+            args.push(format!("--platform={platform}"));
+        }
+        if let Some(language) = settings.language {
+            args.push(format!("--language={}", language.as_cppcheck_arg()));
+        }
+        for addon in &self.addons {
+            // y2038 findings do not apply with a 64-bit time_t
+            let skipped = *addon == Addon::Y2038 && settings.time_bits_64;
+            if addon.applies_to(settings.language) && !skipped {
+                args.push(format!("--addon={}", addon.as_cppcheck_arg()));
+            }
+        }
+        args.extend(
+            self.custom_addons
+                .iter()
+                .map(|a| format!("--addon={}", a.display())),
+        );
+        if self.naming.is_some() && Addon::Naming.applies_to(settings.language) {
+            args.push(format!("--addon={}", self.naming_config().display()));
+        }
+        args.extend(
+            self.rule_files
+                .iter()
+                .map(|r| format!("--rule-file={}", r.display())),
+        );
+        if self.inline_rules.is_some() {
+            args.push(format!(
+                "--rule-file={}",
+                self.inline_rules_file().display()
+            ));
+        }
+        args.extend(self.defines.iter().map(|d| format!("-D{d}")));
+        args.extend(self.undefines.iter().map(|u| format!("-U{u}")));
+        args.extend(
+            self.include_dirs
+                .iter()
+                .map(|i| format!("-I{}", i.display())),
+        );
+        args.extend(
+            self.forced_includes
+                .iter()
+                .map(|i| format!("--include={}", i.display())),
+        );
+        args.extend(self.libraries.iter().map(|l| format!("--library={l}")));
+        if !self.suppressions.is_empty() {
+            args.push(format!(
+                "--suppressions-list={}",
+                self.suppressions_list().display()
+            ));
+        }
+        args
+    }
+
+    fn suppressions_list(&self) -> PathBuf {
+        self.work_dir.join("cppcheck_suppressions.txt")
+    }
+
+    fn naming_config(&self) -> PathBuf {
+        self.work_dir.join("naming.json")
+    }
+
+    fn inline_rules_file(&self) -> PathBuf {
+        self.work_dir.join("cppcheck_rules.xml")
+    }
+
+    /// Names of the addons run, for messages.
+    pub fn addon_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .addons
+            .iter()
+            .map(|a| a.as_cppcheck_arg().to_string())
+            .collect();
+        if self.naming.is_some() {
+            names.push(Addon::Naming.as_cppcheck_arg().to_string());
+        }
+        names.extend(
+            self.custom_addons
+                .iter()
+                .filter_map(|a| Some(a.file_stem()?.to_str()?.to_string())),
+        );
+        names
+    }
+
+    /// Runs cppcheck over the files of `invocation`.
+    pub fn run(&self, index: usize, invocation: &Invocation) -> io::Result<Report> {
+        let start = std::time::Instant::now();
+        let file_list = self.work_dir.join(format!("cppcheck_files_{index}.txt"));
+        let output_path = self.work_dir.join(format!("cppcheck_error_{index}.xml"));
+        let listing = invocation
+            .files
+            .iter()
+            .map(|f| f.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(&file_list, listing)?;
+        if !self.suppressions.is_empty() {
+            std::fs::write(self.suppressions_list(), self.suppressions.join("\n"))?;
+        }
+        if let Some(naming) = &self.naming {
+            std::fs::write(self.naming_config(), naming.addon_json())?;
+        }
+        if let Some(rules) = &self.inline_rules {
+            std::fs::write(self.inline_rules_file(), rules)?;
+        }
+
+        let mut command = Command::new(&self.executable);
+        command
+            .args(self.args(invocation, &file_list, &output_path))
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped());
+        // only enable caching if cache_path is set
+        if let Some(cache_path) = &self.cache_path {
+            let build_dir = cache_path.join(invocation.cache_key());
+            std::fs::create_dir_all(&build_dir)?;
+            command.arg(format!("--cppcheck-build-dir={}", build_dir.display()));
+        }
+        log::debug!("Running cppcheck START :: {:?}", start.elapsed());
+        log::debug!("Command: {command:?}");
+        let output = command.output();
+        log::debug!("Ran cppcheck END :: {:?}", start.elapsed());
+        log::trace!("{:#?}", output);
+        let output = output?;
+        io::stderr().write_all(&output.stderr)?;
+        Ok(Report {
+            path: output_path,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+#[test]
+fn t_addons_only_for_c() {
+    use crate::language::Language;
+
+    let cppcheck = Cppcheck {
+        addons: vec![Addon::Misra, Addon::Cert],
+        work_dir: PathBuf::from("work"),
+        naming: Some(Naming::default()),
+        ..Cppcheck::default()
+    };
+    let invocation = |language| Invocation {
+        settings: Settings {
+            language: Some(language),
+            ..Settings::default()
+        },
+        files: vec![],
+    };
+    let args = |language| {
+        cppcheck.args(
+            &invocation(language),
+            Path::new("files.txt"),
+            Path::new("out.xml"),
+        )
+    };
+    assert!(args(Language::C).contains(&"--addon=misra".to_string()));
+    assert!(args(Language::C).contains(&"--addon=cert".to_string()));
+    assert_eq!(
+        args(Language::Cpp)
+            .iter()
+            .filter(|a| a.starts_with("--addon"))
+            .collect::<Vec<_>>(),
+        ["--addon=work/naming.json"]
+    );
+
+    // y2038 is skipped for the files built with a 64-bit time_t
+    let cppcheck = Cppcheck {
+        addons: vec![Addon::Y2038],
+        ..Cppcheck::default()
+    };
+    let mut invocation = invocation(Language::C);
+    let args = |invocation: &Invocation| {
+        cppcheck.args(invocation, Path::new("files.txt"), Path::new("out.xml"))
+    };
+    assert!(args(&invocation).contains(&"--addon=y2038".to_string()));
+    invocation.settings.time_bits_64 = true;
+    assert!(!args(&invocation).iter().any(|a| a.starts_with("--addon")));
+}
+
+#[test]
+fn t_cache_key() {
+    use crate::language::Language;
+
+    let invocation = |settings| Invocation {
+        settings,
+        files: vec![],
+    };
+    let c99 = Settings {
+        std: Some("c99".to_string()),
+        language: Some(Language::C),
+        ..Settings::default()
+    };
+    assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    let key = invocation(c99.clone()).cache_key();
+    assert_eq!(key, format!("{:016x}", fnv1a(b"c99\0\0c\0")));
+    let time_bits_64 = Settings {
+        time_bits_64: true,
+        ..c99
+    };
+    assert_ne!(invocation(time_bits_64).cache_key(), key);
+}
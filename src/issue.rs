@@ -1,3 +1,4 @@
+#![allow(dead_code)]
 use crate::result::Mark;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -0,0 +1,251 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+pub enum Language {
+    #[serde(rename = "c")]
+    C,
+    #[serde(rename = "c++", alias = "cpp")]
+    Cpp,
+}
+
+impl Language {
+    /// Value accepted by cppcheck's `--language` flag.
+    pub fn as_cppcheck_arg(self) -> &'static str {
+        match self {
+            Language::C => "c",
+            Language::Cpp => "c++",
+        }
+    }
+}
+
+/// What a file extension stands for, named after clang's `-x` values.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+pub enum SourceKind {
+    #[serde(rename = "c")]
+    C,
+    #[serde(rename = "c++", alias = "cpp")]
+    Cpp,
+    #[serde(rename = "c-header")]
+    CHeader,
+    #[serde(rename = "c++-header", alias = "cpp-header")]
+    CppHeader,
+    /// A header whose language is guessed from its contents.
+    #[serde(rename = "header")]
+    Header,
+}
+
+impl SourceKind {
+    pub fn is_header(self) -> bool {
+        matches!(
+            self,
+            SourceKind::CHeader | SourceKind::CppHeader | SourceKind::Header
+        )
+    }
+
+    /// The language implied by the extension alone, if any.
+    pub fn language(self) -> Option<Language> {
+        match self {
+            SourceKind::C | SourceKind::CHeader => Some(Language::C),
+            SourceKind::Cpp | SourceKind::CppHeader => Some(Language::Cpp),
+            SourceKind::Header => None,
+        }
+    }
+}
+
+const DEFAULT_EXTENSIONS: &[(&str, SourceKind)] = &[
+    ("c", SourceKind::C),
+    ("cpp", SourceKind::Cpp),
+    ("cc", SourceKind::Cpp),
+    ("cxx", SourceKind::Cpp),
+    ("c++", SourceKind::Cpp),
+    ("C", SourceKind::Cpp),
+    ("cu", SourceKind::Cpp),
+    ("h", SourceKind::Header),
+    ("hpp", SourceKind::CppHeader),
+    ("hh", SourceKind::CppHeader),
+    ("hxx", SourceKind::CppHeader),
+    ("h++", SourceKind::CppHeader),
+    ("ipp", SourceKind::CppHeader),
+    ("inl", SourceKind::CppHeader),
+];
+
+/// Extension to [`SourceKind`] table, extensions are case sensitive.
+#[derive(Debug)]
+pub struct Extensions(HashMap<String, SourceKind>);
+
+impl Extensions {
+    /// The default table, with `overrides` applied. A `None` override drops the extension.
+    pub fn new(overrides: &BTreeMap<String, Option<SourceKind>>) -> Self {
+        let mut table: HashMap<_, _> = DEFAULT_EXTENSIONS
+            .iter()
+            .map(|(ext, kind)| (ext.to_string(), *kind))
+            .collect();
+        for (ext, kind) in overrides {
+            let ext = ext.trim_start_matches('.').to_string();
+            match kind {
+                Some(kind) => table.insert(ext, *kind),
+                None => table.remove(&ext),
+            };
+        }
+        Extensions(table)
+    }
+
+    pub fn kind(&self, path: &Path) -> Option<SourceKind> {
+        self.0.get(path.extension()?.to_str()?).copied()
+    }
+}
+
+/// The language of a file, reading headers without an implied language.
+pub fn language_of(path: &Path, kind: SourceKind) -> Language {
+    kind.language().unwrap_or_else(|| {
+        let src = std::fs::read(path).unwrap_or_default();
+        if looks_like_cpp(&String::from_utf8_lossy(&src)) {
+            Language::Cpp
+        } else {
+            Language::C
+        }
+    })
+}
+
+/// Keywords and tokens that do not occur in C code.
+const CPP_MARKERS: &[&str] = &[
+    "class",
+    "namespace",
+    "template",
+    "typename",
+    "public:",
+    "private:",
+    "protected:",
+    "virtual",
+    "nullptr",
+    "constexpr",
+    "operator",
+    "::",
+];
+
+/// Guesses whether a header is C++ rather than C from its contents.
+pub fn looks_like_cpp(src: &str) -> bool {
+    let code = strip_comments(src);
+    code.lines().map(str::trim).any(|line| {
+        if let Some(directive) = line.strip_prefix('#') {
+            // extension-less standard headers, e.g. `<vector>`
+            return directive
+                .trim_start()
+                .strip_prefix("include")
+                .map(str::trim)
+                .and_then(|h| h.strip_prefix('<'))
+                .and_then(|h| h.strip_suffix('>'))
+                .map(|h| !h.contains('.'))
+                .unwrap_or_default();
+        }
+        CPP_MARKERS
+            .iter()
+            .any(|marker| contains_token(line, marker))
+    })
+}
+
+fn contains_token(line: &str, token: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(token).any(|(i, _)| {
+        let before = line[..i].chars().next_back();
+        let after = line[i + token.len()..].chars().next();
+        let clean_start = !token.starts_with(is_ident) || !before.is_some_and(is_ident);
+        let clean_end = !token.ends_with(is_ident) || !after.is_some_and(is_ident);
+        clean_start && clean_end
+    })
+}
+
+/// Removes `//` and `/* */` comments, keeping line breaks.
+fn strip_comments(src: &str) -> String {
+    let mut out = String::with_capacity(src.len());
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while chars.peek().map(|&c| c != '\n').unwrap_or_default() {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[test]
+fn t_looks_like_cpp() {
+    let c_header = r#"
+        #ifndef UART_H
+        #define UART_H
+        #ifdef __cplusplus
+        extern "C" {
+        #endif
+        #include <stdint.h>
+        /* the class of the device, see std::uart */
+        int uart_write(const uint8_t *buf, int len); // namespace-less
+        #ifdef __cplusplus
+        }
+        #endif
+        #endif
+    "#;
+    assert!(!looks_like_cpp(c_header));
+    assert!(looks_like_cpp("#include <vector>\n"));
+    assert!(looks_like_cpp("namespace net {\n}\n"));
+    assert!(looks_like_cpp("int x = ::getpid();\n"));
+    assert!(!looks_like_cpp("int classify(int subclass);\n"));
+}
+
+#[test]
+fn t_extensions() {
+    let extensions = Extensions::new(&BTreeMap::from([
+        (".h".to_string(), Some(SourceKind::CHeader)),
+        ("tpp".to_string(), Some(SourceKind::CppHeader)),
+        ("cu".to_string(), None),
+    ]));
+    let kind = |path: &str| extensions.kind(Path::new(path));
+    assert_eq!(kind("a.h"), Some(SourceKind::CHeader));
+    assert_eq!(kind("a.tpp"), Some(SourceKind::CppHeader));
+    assert_eq!(kind("a.cu"), None);
+    assert_eq!(kind("a.cc"), Some(SourceKind::Cpp));
+    assert_eq!(kind("a.H"), None);
+    assert_eq!(kind("Makefile"), None);
+}
+
+#[test]
+fn t_language_of() {
+    let dir = tempfile::tempdir().unwrap();
+    let c_header = dir.path().join("uart.h");
+    std::fs::write(&c_header, "int uart_write(const char *buf);\n").unwrap();
+    let cpp_header = dir.path().join("net.h");
+    std::fs::write(&cpp_header, "namespace net {\n}\n").unwrap();
+    assert_eq!(language_of(&c_header, SourceKind::Header), Language::C);
+    assert_eq!(language_of(&cpp_header, SourceKind::Header), Language::Cpp);
+    // the extension wins over the contents
+    assert_eq!(language_of(&cpp_header, SourceKind::CHeader), Language::C);
+    assert_eq!(language_of(&c_header, SourceKind::Cpp), Language::Cpp);
+    // unreadable headers are taken for C
+    assert_eq!(
+        language_of(&dir.path().join("gone.h"), SourceKind::Header),
+        Language::C
+    );
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use globset::GlobSet;
+use walkdir::WalkDir;
+
+use crate::config::glob_set;
+
+/// cppcheck library configs and the headers which imply them.
+/// `std` is always loaded by cppcheck and therefore not listed.
+const LIBRARIES: &[(&str, &[&str])] = &[
+    (
+        "posix",
+        &[
+            "unistd.h",
+            "pthread.h",
+            "fcntl.h",
+            "dirent.h",
+            "dlfcn.h",
+            "poll.h",
+            "semaphore.h",
+            "syslog.h",
+            "termios.h",
+            "regex.h",
+            "netdb.h",
+            "glob.h",
+            "sched.h",
+            "strings.h",
+            "spawn.h",
+            "pwd.h",
+            "grp.h",
+            "libgen.h",
+            "sys/**",
+            "netinet/**",
+            "arpa/**",
+        ],
+    ),
+    (
+        "gnu",
+        &[
+            "features.h",
+            "malloc.h",
+            "execinfo.h",
+            "error.h",
+            "getopt.h",
+        ],
+    ),
+    ("bsd", &["bsd/**"]),
+    (
+        "windows",
+        &[
+            "windows.h",
+            "winsock2.h",
+            "ws2tcpip.h",
+            "windef.h",
+            "winbase.h",
+            "winuser.h",
+            "tchar.h",
+            "shlwapi.h",
+            "shellapi.h",
+        ],
+    ),
+    ("mfc", &["afx*.h"]),
+    ("microsoft_sal", &["sal.h"]),
+    (
+        "qt",
+        &[
+            "Qt*/**",
+            "QtCore",
+            "QtGui",
+            "QtWidgets",
+            "QtNetwork",
+            "QtGlobal",
+            "QObject",
+            "QString",
+            "QStringList",
+            "QByteArray",
+            "QList",
+            "QVector",
+            "QMap",
+            "QHash",
+            "QSet",
+            "QVariant",
+            "QDebug",
+            "QFile",
+            "QDir",
+            "QTimer",
+            "QThread",
+            "QMutex",
+            "QDateTime",
+            "QUrl",
+            "QSettings",
+            "QCoreApplication",
+            "QApplication",
+            "QWidget",
+            "QMainWindow",
+            "QDialog",
+            "QPushButton",
+            "QLabel",
+            "QVBoxLayout",
+            "QHBoxLayout",
+            "QPainter",
+            "QPixmap",
+            "QImage",
+        ],
+    ),
+    ("boost", &["boost/**"]),
+    ("gtk", &["gtk/**", "gdk/**", "glib.h", "glib/**", "gio/**"]),
+    ("sqlite3", &["sqlite3.h"]),
+    ("openssl", &["openssl/**"]),
+    ("zlib", &["zlib.h"]),
+    ("libcurl", &["curl/**"]),
+    ("lua", &["lua.h", "lauxlib.h", "lualib.h", "lua.hpp"]),
+    ("python", &["Python.h"]),
+    ("ruby", &["ruby.h", "ruby/**"]),
+    ("opencv2", &["opencv2/**"]),
+    ("opengl", &["GL/**"]),
+    ("openmp", &["omp.h"]),
+    ("pcre", &["pcre.h"]),
+    ("sdl", &["SDL.h", "SDL2/**", "SDL/**"]),
+    ("sfml", &["SFML/**"]),
+    ("googletest", &["gtest/**", "gmock/**"]),
+    ("cppunit", &["cppunit/**"]),
+    ("tinyxml2", &["tinyxml2.h"]),
+    ("wxwidgets", &["wx/**"]),
+    ("cairo", &["cairo.h", "cairo/**"]),
+    ("icu", &["unicode/**"]),
+    ("libsigc++", &["sigc++/**"]),
+    ("emscripten", &["emscripten.h", "emscripten/**"]),
+    ("dpdk", &["rte_*.h"]),
+    ("avr", &["avr/**"]),
+    ("ntl", &["NTL/**"]),
+    ("nspr", &["nspr.h", "prtypes.h"]),
+    ("zephyr", &["zephyr/**"]),
+];
+
+fn patterns() -> Vec<(&'static str, GlobSet)> {
+    LIBRARIES
+        .iter()
+        .map(|(name, headers)| {
+            (
+                *name,
+                glob_set(headers).expect("library header patterns are valid globs"),
+            )
+        })
+        .collect()
+}
+
+/// Libraries implied by the `<header>` includes of the analyzed files which the
+/// repository does not provide, see [`Resolution::external`].
+///
+/// [`Resolution::external`]: crate::includes::Resolution::external
+pub fn detect<'a>(headers: impl IntoIterator<Item = &'a String>) -> BTreeSet<&'static str> {
+    let patterns = patterns();
+    let mut detected = BTreeSet::new();
+    for header in headers {
+        for (name, set) in &patterns {
+            if set.is_match(header) {
+                detected.insert(*name);
+            }
+        }
+    }
+    detected
+}
+
+/// Every `*.cfg` file below `dir`.
+pub fn local_configs(dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| p.extension().map(|x| x == "cfg").unwrap_or_default())
+        .collect()
+}
+
+#[test]
+fn t_detect() {
+    let headers = [
+        "stdio.h",
+        "sys/socket.h",
+        "QString",
+        "QtCore/QObject",
+        "QueueTypes.h",
+        "boost/optional.hpp",
+        "sqlite3.h",
+    ]
+    .map(String::from);
+    assert_eq!(
+        detect(&headers).into_iter().collect::<Vec<_>>(),
+        ["boost", "posix", "qt", "sqlite3"]
+    );
+
+    // quoted includes and headers of the repository imply no library
+    let code_path = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let file = code_path.join("src/main.c");
+    let directives = [(
+        &file,
+        crate::includes::directives(
+            "#include \"error.h\"\n#include <library.rs>\n#include <glob.h>\n",
+        ),
+    )];
+    let resolution = crate::includes::resolve(code_path, &directives, &[], false);
+    assert_eq!(resolution.external.iter().collect::<Vec<_>>(), ["glob.h"]);
+}
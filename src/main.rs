@@ -1,17 +1,41 @@
+mod addon;
+mod catalog;
 mod config;
+mod coverage;
 mod cppcheck;
+mod deepsource;
+mod deviation;
 mod fmtlogger;
+mod generate;
+mod includes;
+mod invocation;
 mod issue;
+mod language;
+mod library;
+mod misra;
+mod policy;
 mod result;
+mod rules;
+mod sources;
+mod validation;
 
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     error::Error,
     path::{Path, PathBuf},
-    process::{self, Command, Stdio},
+    process,
 };
 
-use crate::config::AnalyzerConfig;
+use crate::{
+    addon::Addon,
+    catalog::Classification,
+    config::TestIssues,
+    deepsource::DeepSourceConfig,
+    invocation::{Cppcheck, Invocation},
+    language::Extensions,
+    sources::Selector,
+    validation::ConfigValidation,
+};
 
 use env_struct::env_struct;
 env_struct! {
@@ -29,37 +53,18 @@ env_struct! {
     }
 }
 
-fn run_cppcheck<'a>(executable: &str, code_path: impl AsRef<str>, output_path: impl AsRef<Path>) {
-    let start = std::time::Instant::now();
-    // ensure the command is run in `sh`
-    let mut command_with_sh = Command::new("sh");
-    // only enable caching if cache_path is set
-    let cppcheck_build_dir = if let Ok(cppcheck_env) = CppcheckEnv::try_load_from_env() {
-        format!("--cppcheck-build-dir={}", cppcheck_env.cppcheck_cache_path)
-    } else {
-        "".to_string()
-    };
-    let code_dir = code_path.as_ref();
-    let output_file = output_path.as_ref().display();
-    // build the command to run
-    command_with_sh
-        .arg("-c")
-        .arg(format!("{executable} {code_dir} -l 6 --addon=misra --xml --output-file={output_file} {cppcheck_build_dir}"))
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit());
-    log::debug!("Running cppcheck START :: {:?}", start.elapsed());
-    log::debug!("Shell command: {command_with_sh:?}");
-    let output = command_with_sh.output();
-    log::debug!("Ran cppcheck END :: {:?}", start.elapsed());
-    log::trace!("{:#?}", output);
-}
-
 fn main() {
     // setup logging
     fmtlogger::default();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("generate-issues") => generate::run(&args[1..]),
+        Some("check-coverage") => coverage::run(&args[1..]),
+        _ => _main(),
+    };
     // all errors are propagated to sentry with backtrace
-    if let Err(err) = _main() {
+    if let Err(err) = result {
         log::error!("error raised: {err}");
         // early exit with status 1
         process::exit(1);
@@ -70,76 +75,458 @@ fn _main() -> Result<(), Box<dyn Error>> {
     let env = Env::load_from_env();
     let toolbox_directory = PathBuf::from(env.toolbox_path);
     let cppcheck_executable = "cppcheck";
-    let cppcheck_output_path = toolbox_directory.join("cppcheck_error.xml");
     let analysis_config_path = toolbox_directory.join("analysis_config.json");
-    let files_set: HashSet<PathBuf> = HashSet::from_iter(
-        std::fs::read_to_string(&analysis_config_path)
-            .ok()
-            .and_then(|s| serde_json::from_str::<AnalyzerConfig>(&s).ok())
-            .map_or_else(
-                || {
-                    log::error!(
-                        "Failed to load analysis config, at `{}`, using empty file list.",
-                        analysis_config_path.display()
-                    );
-                    Vec::default()
-                },
-                AnalyzerConfig::cxx_files,
-            ),
-    );
+    let code_path = Path::new(&env.code_path);
+    let mut errors = vec![];
+    let mut analyzer_config = None;
+    if let Ok(src) = std::fs::read_to_string(&analysis_config_path) {
+        let validated = validation::validate(&src);
+        for diagnostic in &validated.diagnostics {
+            log::warn!("{}: {diagnostic}", analysis_config_path.display());
+        }
+        if validated.mode == ConfigValidation::Strict && !validated.diagnostics.is_empty() {
+            return Err(format!(
+                "`{}` has {} problem(s), see above",
+                analysis_config_path.display(),
+                validated.diagnostics.len()
+            )
+            .into());
+        }
+        errors.extend(
+            validated
+                .diagnostics
+                .iter()
+                .map(|diagnostic| result::AnalysisError {
+                    hmrn: format!("analysis_config.json: {diagnostic}"),
+                    level: result::ErrorLevel::Warning,
+                }),
+        );
+        if validated.config.is_none() {
+            let hmrn = "analysis_config.json: the whole config was ignored, files are discovered \
+                by walking the repository and analyzed with the default settings";
+            log::error!("{hmrn}");
+            errors.push(result::AnalysisError {
+                hmrn: hmrn.to_string(),
+                level: result::ErrorLevel::Error,
+            });
+        }
+        analyzer_config = validated.config;
+    }
+    let mut analyzer_meta = analyzer_config
+        .as_mut()
+        .map(|config| std::mem::take(&mut config.analyzer_meta))
+        .unwrap_or_default();
+    let grp = match &analyzer_meta.misra_grp {
+        Some(path) => {
+            let (grp, problems) = misra::Grp::load(&code_path.join(path))?;
+            for problem in problems {
+                log::warn!("{}: {problem}", path.display());
+                errors.push(result::AnalysisError {
+                    hmrn: format!("{}: {problem}", path.display()),
+                    level: result::ErrorLevel::Warning,
+                });
+            }
+            grp
+        }
+        None => misra::Grp::default(),
+    };
+    let mut deviations = match &analyzer_meta.misra_deviations {
+        Some(path) => {
+            let deviations = deviation::Deviations::load(&code_path.join(path), &grp)?;
+            let today = deviation::Date::today();
+            let expired: Vec<_> = deviations.expired(today).collect();
+            for deviation in &expired {
+                log::error!(
+                    "{}: deviation {} expired on {}",
+                    path.display(),
+                    deviation.label,
+                    deviation.expires.unwrap_or(today)
+                );
+            }
+            if !expired.is_empty() {
+                return Err(format!(
+                    "`{}` has {} expired deviation(s), see above",
+                    path.display(),
+                    expired.len()
+                )
+                .into());
+            }
+            deviations
+        }
+        None => deviation::Deviations::default(),
+    };
+    // disapplied guidelines are not checked at all
+    analyzer_meta
+        .disabled_issues
+        .extend(grp.disapplied().map(String::from));
+    analyzer_meta.resolve_repository_files(code_path)?;
+    let custom_addons = addon::CustomAddons::load(code_path, &analyzer_meta.custom_addons)?;
+    let (custom_rules, rule_problems) = rules::CustomRules::load(
+        code_path,
+        &analyzer_meta.rule_files,
+        &analyzer_meta.rules,
+        custom_addons.entries(),
+    )?;
+    for problem in rule_problems {
+        log::warn!("{problem}");
+        errors.push(result::AnalysisError {
+            hmrn: format!("analysis_config.json: {problem}"),
+            level: result::ErrorLevel::Warning,
+        });
+    }
+    let policy =
+        analyzer_meta.issue_policy([custom_addons.entries(), custom_rules.entries()].concat())?;
+    for key in policy.unknown_keys() {
+        log::warn!("`{key}` matches no known issue");
+        errors.push(result::AnalysisError {
+            hmrn: format!("analysis_config.json: `{key}` matches no known issue"),
+            level: result::ErrorLevel::Warning,
+        });
+    }
+    let extensions = Extensions::new(&analyzer_meta.extensions);
+    let patterns = DeepSourceConfig::load(code_path).patterns()?;
+    let selector = Selector {
+        code_path,
+        extensions: &extensions,
+        patterns: &patterns,
+        limits: &analyzer_meta.limits(),
+    };
+    let (discovery_mode, candidates) = match analyzer_config {
+        Some(config) => (result::DiscoveryMode::AnalysisConfig, config.into_files()),
+        None => {
+            log::error!(
+                "Failed to load analysis config, at `{}`, discovering files in `{}`.",
+                analysis_config_path.display(),
+                code_path.display()
+            );
+            (result::DiscoveryMode::Walk, sources::walk(code_path))
+        }
+    };
+    let selection = selector.select(candidates.iter().cloned());
+    let files_set: HashSet<PathBuf> = selection.files.iter().cloned().collect();
 
-    run_cppcheck(cppcheck_executable, env.code_path, &cppcheck_output_path);
+    let directives: Vec<_> = includes::scan(&files_set).collect();
+    let resolution = includes::resolve(
+        code_path,
+        &directives,
+        &analyzer_meta.include_paths,
+        analyzer_meta.auto_include_paths,
+    );
+    let mut addons = analyzer_meta.addons.clone();
+    // y2038 findings do not apply with a 64-bit time_t
+    let time_bits_64 = if !addons.contains(&Addon::Y2038) {
+        addon::TimeBits64::default()
+    } else if let Some(define) = addon::defines_time_bits_64(&analyzer_meta.defines) {
+        log::info!("`{define}` in `defines` makes time_t 64-bit, skipping the y2038 addon");
+        addons.retain(|a| *a != Addon::Y2038);
+        addon::TimeBits64::default()
+    } else {
+        addon::TimeBits64::scan(&candidates)
+    };
+    let included = resolution.included_from(
+        files_set
+            .iter()
+            .filter(|file| extensions.kind(file).is_some_and(|kind| !kind.is_header())),
+    );
+    let sources = analyzer_meta
+        .settings_for(code_path, &files_set)?
+        .into_iter()
+        .filter_map(|(mut settings, file)| {
+            let kind = extensions.kind(file)?;
+            // headers reached from an analyzed translation unit are checked as part of it
+            if kind.is_header() && included.contains(&includes::normalize(file)) {
+                return None;
+            }
+            settings.language = settings
+                .language
+                .or_else(|| Some(language::language_of(file, kind)));
+            if let Some(origin) = time_bits_64.origin(file) {
+                log::debug!(
+                    "`{}` has a 64-bit time_t ({})",
+                    file.display(),
+                    origin.display()
+                );
+                settings.time_bits_64 = true;
+            }
+            Some((settings, file))
+        });
+    let invocations = Invocation::group(sources);
+    let mut libraries = analyzer_meta.libraries.clone();
+    if analyzer_meta.auto_libraries {
+        for library in library::detect(&resolution.external) {
+            if !libraries.iter().any(|l| l == library) {
+                libraries.push(library.to_string());
+            }
+        }
+    }
+    if let Some(library_dir) = &analyzer_meta.library_dir {
+        libraries.extend(
+            library::local_configs(&code_path.join(library_dir))
+                .iter()
+                .map(|cfg| cfg.display().to_string()),
+        );
+    }
+    log::debug!("libraries: {libraries:?}");
+    let mut cppcheck = Cppcheck {
+        executable: cppcheck_executable.to_string(),
+        work_dir: toolbox_directory.clone(),
+        cache_path: CppcheckEnv::try_load_from_env()
+            .ok()
+            .map(|cppcheck_env| PathBuf::from(cppcheck_env.cppcheck_cache_path)),
+        defines: analyzer_meta.defines,
+        undefines: analyzer_meta.undefines,
+        include_dirs: resolution.include_dirs,
+        forced_includes: analyzer_meta.forced_includes,
+        libraries,
+        suppressions: policy.suppressions(),
+        // addons none of whose checks are enabled are not run
+        addons: addons
+            .into_iter()
+            .filter(|addon| {
+                catalog::entries()
+                    .iter()
+                    .any(|e| Addon::of(e.id) == Some(*addon) && policy.is_enabled(e.id, e.code))
+            })
+            .collect(),
+        custom_addons: custom_addons.paths.clone(),
+        rule_files: custom_rules.files.clone(),
+        inline_rules: custom_rules.inline.clone(),
+        naming: analyzer_meta.naming.filter(|naming| {
+            naming
+                .checks()
+                .any(|id| catalog::lookup(id).is_some_and(|e| policy.is_enabled(e.id, e.code)))
+        }),
+    };
+    let addon_names = cppcheck.addon_names();
+    let python_missing = !addon_names.is_empty() && !addon::python_available();
+    if python_missing {
+        // cppcheck would report no findings for them, as if the code complied
+        let hmrn = format!(
+            "python3 is not available, addon(s) {} not run",
+            addon_names.join(", ")
+        );
+        log::error!("{hmrn}");
+        errors.push(result::AnalysisError {
+            hmrn,
+            level: result::ErrorLevel::Error,
+        });
+        cppcheck.addons.clear();
+        cppcheck.naming = None;
+        cppcheck.custom_addons.clear();
+    }
+    let mut cppcheck_outputs = vec![];
+    for (index, invocation) in invocations.iter().enumerate() {
+        log::debug!("{:?}", invocation.settings);
+        cppcheck_outputs.push(cppcheck.run(index, invocation)?);
+    }
+    // addon name -> what went wrong, first seen
+    let mut failed_addons = BTreeMap::<String, String>::new();
+    for report in &cppcheck_outputs {
+        for (addon, exception) in addon::tracebacks(&report.stderr) {
+            failed_addons.entry(addon).or_insert(exception);
+        }
+    }
 
     log::debug!("{:#?}", files_set);
     let mut issue_occurrences = vec![];
-    if let Some(cppcheck_results) = std::fs::read_to_string(cppcheck_output_path)
-        .ok()
-        .map(|src| quick_xml::de::from_str::<cppcheck::Results>(&src).unwrap())
-    {
+    let mut findings = result::Findings::default();
+    let mut misra_violations = BTreeMap::<&str, usize>::new();
+    for report in cppcheck_outputs {
+        let Some(cppcheck_results) = std::fs::read_to_string(&report.path)
+            .ok()
+            .map(|src| quick_xml::de::from_str::<cppcheck::Results>(&src).unwrap())
+        else {
+            continue;
+        };
         // log::debug!("{:?}", cppcheck_results);
         for error in cppcheck_results.errors.error {
-            if let Some(issue_code) = cppcheck::mapping(&error.id) {
-                let Some(location) = error.location.as_ref().and_then(|l| l.get(0)) else {
-                    continue;
-                };
-                if !files_set.contains(&PathBuf::from(&location.file)) {
+            if error.id == "internalError" {
+                if let Some(addon) = addon::failed_addon(&error.msg) {
+                    failed_addons.entry(addon).or_insert(error.msg);
                     continue;
                 }
-                let issue_text = if error.msg.starts_with("misra") {
-                    format!(
-                        "{} {}",
-                        error.id,
-                        error
-                            .symbol
-                            .get(0)
-                            .map(String::as_str)
-                            .unwrap_or_else(|| "")
-                    )
-                } else {
+                let file = error
+                    .location
+                    .iter()
+                    .flatten()
+                    .next()
+                    .map(|l| l.file.as_str());
+                let hmrn = format!(
+                    "cppcheck failed on `{}`: {}",
+                    file.unwrap_or_default(),
                     error.msg
-                };
-                issue_occurrences.push(result::Issue {
-                    issue_text,
-                    issue_code,
-                    location: result::Location {
-                        path: location.file.clone(),
-                        position: result::Position {
-                            begin: result::Mark {
-                                line: location.line,
-                                column: location.column,
-                            },
-                            end: result::Mark {
-                                line: location.line,
-                                column: location.column,
-                            },
-                        },
-                    },
+                );
+                log::error!("{hmrn}");
+                errors.push(result::AnalysisError {
+                    hmrn,
+                    level: result::ErrorLevel::Error,
                 });
+                continue;
+            }
+            let mapped = catalog::lookup(&error.id)
+                .copied()
+                .or_else(|| custom_addons.lookup(&error.id))
+                .or_else(|| custom_rules.lookup(&error.id));
+            let entry = mapped.unwrap_or(catalog::UNMAPPED);
+            let Some(location) = error.location.as_ref().and_then(|l| l.first()) else {
+                continue;
+            };
+            let path = PathBuf::from(&location.file);
+            if !files_set.contains(&path) {
+                continue;
+            }
+            let issue_text = if let Some(rule) = error.id.strip_prefix("misra-c2012-") {
+                // the addon numbers rules after MISRA C:2012 whatever the version
+                format!(
+                    "misra-c{}-{rule} {}",
+                    analyzer_meta.misra_version.year(),
+                    error.symbol.first().map(String::as_str).unwrap_or_default()
+                )
+            } else if let Some(pattern) =
+                (cppcheck.naming.as_ref()).and_then(|naming| naming.pattern(&error.id))
+            {
+                format!("{}, expected to match `{pattern}`", error.msg)
+            } else if entry.code == catalog::UNMAPPED.code {
+                format!("{} [{}]", error.msg, error.id)
+            } else {
+                error.msg
+            };
+            if !findings.insert(&error.id, entry.code, location, &issue_text) {
+                continue;
             }
+            if mapped.is_none() && !analyzer_meta.report_unmapped {
+                continue;
+            }
+            // suppressions cover most checks, this also catches those reported anyway
+            if !policy.is_enabled(&error.id, entry.code) {
+                continue;
+            }
+            let test_code = patterns.is_test(path.strip_prefix(code_path).unwrap_or(&path));
+            let misra_classification = grp.classification(&entry);
+            let severity = match (test_code, analyzer_meta.test_issues) {
+                (true, TestIssues::Skip) => continue,
+                (true, TestIssues::Downgrade) => result::Severity::Minor,
+                _ => policy
+                    .severity(&error.id, entry.code)
+                    .or_else(|| {
+                        // a re-categorized rule takes the severity of its new classification
+                        misra_classification
+                            .filter(|c| entry.misra != Some(*c))
+                            .map(Classification::severity)
+                    })
+                    .or(entry.severity)
+                    .unwrap_or_else(|| result::Severity::from_cppcheck(&error.severity)),
+            };
+            let mut deviation = None;
+            if entry.misra.is_some() {
+                *misra_violations.entry(entry.id).or_default() += 1;
+                deviation = deviations.apply(
+                    entry.id,
+                    path.strip_prefix(code_path).unwrap_or(&path),
+                    location.line,
+                    || {
+                        let src = std::fs::read_to_string(&path).ok()?;
+                        deviation::enclosing_function(&src, location.line)
+                    },
+                );
+            }
+            issue_occurrences.push(result::Issue {
+                issue_text,
+                issue_code: entry.code.to_string(),
+                location: result::Location {
+                    path: location.file.clone(),
+                    position: result::Position {
+                        begin: result::Mark {
+                            line: location.line,
+                            column: location.column,
+                        },
+                        end: result::Mark {
+                            line: location.line,
+                            column: location.column,
+                        },
+                    },
+                },
+                severity,
+                test_code,
+                deviation,
+                misra_classification,
+            });
         }
     }
 
-    let json_output = serde_json::to_string(&issue_occurrences);
+    let unmapped_checks = findings.unmapped_checks;
+    if !unmapped_checks.is_empty() {
+        log::warn!("checks missing from the catalog: {unmapped_checks:?}");
+    }
+    for (addon, failure) in &failed_addons {
+        let hmrn = format!("the `{addon}` addon failed, its findings are missing: {failure}");
+        log::error!("{hmrn}");
+        errors.push(result::AnalysisError {
+            hmrn,
+            level: result::ErrorLevel::Error,
+        });
+    }
+    let unused_deviations: Vec<_> = deviations.unused().map(String::from).collect();
+    for label in &unused_deviations {
+        log::warn!("deviation {label} matches no violation");
+        errors.push(result::AnalysisError {
+            hmrn: format!("deviation {label} matches no violation"),
+            level: result::ErrorLevel::Warning,
+        });
+    }
+    let misra_compliance = (cppcheck.addons.contains(&Addon::Misra)
+        && !failed_addons.contains_key(Addon::Misra.as_cppcheck_arg())
+        && invocations
+            .iter()
+            .any(|i| Addon::Misra.applies_to(i.settings.language)))
+    .then(|| {
+        misra::compliance_matrix(
+            analyzer_meta.misra_version,
+            &grp,
+            &policy,
+            &misra_violations,
+            &deviations.counts(),
+        )
+    });
+    let relative = |p: &Path| p.strip_prefix(code_path).unwrap_or(p).display().to_string();
+    let extra_data = result::ExtraData {
+        discovery_mode,
+        include_dirs: cppcheck.include_dirs.iter().map(|d| relative(d)).collect(),
+        unresolved_includes: resolution
+            .unresolved
+            .iter()
+            .map(|(include, files)| (include.clone(), files.iter().map(|f| relative(f)).collect()))
+            .collect(),
+        libraries: cppcheck
+            .libraries
+            .iter()
+            .map(|l| relative(Path::new(l)))
+            .collect(),
+        skipped_files: selection
+            .skipped
+            .iter()
+            .map(|(path, reason)| result::SkippedFile {
+                path: relative(path),
+                reason: reason.to_string(),
+            })
+            .collect(),
+        unmapped_checks,
+        misra_compliance,
+        unused_deviations,
+    };
+    let analysis_result = result::AnalysisResult {
+        // addons which did not run report no findings, as if the code complied
+        is_passed: !python_missing
+            && failed_addons.is_empty()
+            && !issue_occurrences
+                .iter()
+                .any(|i| i.fails(analyzer_meta.misra_fail_on)),
+        issues: issue_occurrences,
+        errors,
+        extra_data,
+    };
+
+    let json_output = serde_json::to_string(&analysis_result);
     log::debug!(
         "{}",
         json_output.as_ref().map(String::as_str).unwrap_or("{}")
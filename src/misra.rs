@@ -0,0 +1,270 @@
+use std::{collections::BTreeMap, error::Error, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    catalog::{self, Classification, Entry, MisraVersion},
+    policy::IssuePolicy,
+};
+
+/// A category of a Guideline Re-categorization Plan.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum GrpCategory {
+    Mandatory,
+    Required,
+    Advisory,
+    /// An advisory guideline the project does not apply.
+    Disapplied,
+}
+
+impl GrpCategory {
+    /// The classification the guideline is checked with, `None` when disapplied.
+    fn classification(self) -> Option<Classification> {
+        match self {
+            GrpCategory::Mandatory => Some(Classification::Mandatory),
+            GrpCategory::Required => Some(Classification::Required),
+            GrpCategory::Advisory => Some(Classification::Advisory),
+            GrpCategory::Disapplied => None,
+        }
+    }
+
+    /// Whether MISRA Compliance:2020 allows re-categorizing a guideline of `classification`
+    /// as `self`: only to a stricter category, or disapplying an advisory one.
+    fn allowed_for(self, classification: Classification) -> bool {
+        matches!(
+            (classification, self),
+            (Classification::Advisory, _)
+                | (
+                    Classification::Required,
+                    GrpCategory::Mandatory | GrpCategory::Required
+                )
+                | (Classification::Mandatory, GrpCategory::Mandatory)
+        )
+    }
+}
+
+/// A Guideline Re-categorization Plan, read from a TOML file mapping guidelines to their
+/// category in the project, e.g. `"Rule 15.5" = "disapplied"` or `"Dir 4.9" = "required"`.
+#[derive(Debug, Default)]
+pub struct Grp {
+    /// Categories by cppcheck id.
+    categories: BTreeMap<&'static str, GrpCategory>,
+}
+
+impl Grp {
+    pub fn load(path: &Path) -> Result<(Self, Vec<String>), Box<dyn Error>> {
+        let src = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read `{}`: {err}", path.display()))?;
+        Ok(Self::parse(&src)?)
+    }
+
+    /// Parses a plan, returning the problems with the guidelines it skipped.
+    pub(crate) fn parse(src: &str) -> Result<(Self, Vec<String>), toml::de::Error> {
+        let plan: BTreeMap<String, GrpCategory> = toml::from_str(src)?;
+        let mut grp = Grp::default();
+        let mut problems = vec![];
+        for (guideline, category) in plan {
+            let Some(entry) = lookup(&guideline) else {
+                problems.push(format!("`{guideline}` is not a known MISRA C guideline"));
+                continue;
+            };
+            let classification = entry.misra.unwrap_or(Classification::Required);
+            if !category.allowed_for(classification) {
+                problems.push(format!(
+                    "`{guideline}` is {classification:?}, it cannot be re-categorized as {category:?}"
+                ));
+                continue;
+            }
+            grp.categories.insert(entry.id, category);
+        }
+        Ok((grp, problems))
+    }
+
+    pub fn category(&self, id: &str) -> Option<GrpCategory> {
+        self.categories.get(id).copied()
+    }
+
+    /// The classification of `entry` in the project, `None` for other than MISRA rules.
+    pub fn classification(&self, entry: &Entry) -> Option<Classification> {
+        let recategorized = self
+            .category(entry.id)
+            .and_then(GrpCategory::classification);
+        recategorized.or(entry.misra)
+    }
+
+    /// cppcheck ids of the guidelines not applied.
+    pub fn disapplied(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.categories
+            .iter()
+            .filter(|(_, category)| **category == GrpCategory::Disapplied)
+            .map(|(id, _)| *id)
+    }
+}
+
+/// Finds a guideline given as `Rule 2.3`, `Dir 4.1`, `2.3` or by its cppcheck id.
+pub fn lookup(guideline: &str) -> Option<&'static Entry<'static>> {
+    let guideline = guideline.trim();
+    let id = if let Some(number) = guideline.strip_prefix("Dir ") {
+        format!("misra-c2012-Dir-{}", number.trim())
+    } else if let Some(number) = guideline.strip_prefix("Rule ") {
+        format!("misra-c2012-{}", number.trim())
+    } else if guideline.starts_with("misra-") {
+        guideline.to_string()
+    } else {
+        format!("misra-c2012-{guideline}")
+    };
+    catalog::lookup(&id).filter(|entry| entry.misra.is_some())
+}
+
+/// The name of a guideline in MISRA documents, `Rule 2.3` or `Dir 4.1`.
+pub fn guideline(id: &str) -> String {
+    let number = id.trim_start_matches("misra-c2012-");
+    match number.strip_prefix("Dir-") {
+        Some(number) => format!("Dir {number}"),
+        None => format!("Rule {number}"),
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Support {
+    Checked,
+    /// Left to the compiler, cppcheck cannot check it.
+    CompilerOnly,
+    /// Turned off in `analyzer_meta`, or beyond the checks of the addon.
+    NotChecked,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Compliance {
+    Compliant,
+    /// Every violation is covered by a deviation.
+    Deviations,
+    Violations,
+    Disapplied,
+    /// Compliance could not be established by the analysis.
+    NotChecked,
+}
+
+/// A row of a MISRA Compliance:2020 guideline compliance summary.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GuidelineCompliance {
+    pub guideline: String,
+    pub classification: Classification,
+    /// The category of the guideline in the project's re-categorization plan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recategorization: Option<GrpCategory>,
+    pub support: Support,
+    pub violations: usize,
+    pub deviations: usize,
+    pub compliance: Compliance,
+}
+
+/// Builds the compliance summary of every guideline of `version`, from the number of
+/// violations and deviated violations of each cppcheck id.
+pub fn compliance_matrix(
+    version: MisraVersion,
+    grp: &Grp,
+    policy: &IssuePolicy,
+    violations: &BTreeMap<&str, usize>,
+    deviations: &BTreeMap<&str, usize>,
+) -> Vec<GuidelineCompliance> {
+    catalog::entries()
+        .iter()
+        .filter(|e| e.misra_since.is_some_and(|since| since <= version))
+        .filter_map(|entry| {
+            let classification = entry.misra?;
+            let recategorization = grp.category(entry.id);
+            let support = if entry.compiler_only {
+                Support::CompilerOnly
+            } else if !entry.checked || !policy.is_enabled(entry.id, entry.code) {
+                Support::NotChecked
+            } else {
+                Support::Checked
+            };
+            let violations = violations.get(entry.id).copied().unwrap_or_default();
+            let deviations = deviations.get(entry.id).copied().unwrap_or_default();
+            let compliance = if recategorization == Some(GrpCategory::Disapplied) {
+                Compliance::Disapplied
+            } else if violations > deviations {
+                Compliance::Violations
+            } else if deviations > 0 {
+                Compliance::Deviations
+            } else if support != Support::Checked {
+                Compliance::NotChecked
+            } else {
+                Compliance::Compliant
+            };
+            Some(GuidelineCompliance {
+                guideline: guideline(entry.id),
+                classification,
+                recategorization,
+                support,
+                violations,
+                deviations,
+                compliance,
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn t_compliance_matrix() {
+    let (grp, problems) = Grp::parse(
+        r#"
+        "Rule 15.5" = "disapplied"
+        "Dir 4.9" = "required"
+        "9.1" = "advisory"
+        "Rule 99.1" = "required"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        problems,
+        [
+            "`9.1` is Mandatory, it cannot be re-categorized as Advisory",
+            "`Rule 99.1` is not a known MISRA C guideline",
+        ]
+    );
+    assert_eq!(grp.disapplied().collect::<Vec<_>>(), ["misra-c2012-15.5"]);
+
+    let disabled: Vec<_> = grp.disapplied().map(String::from).collect();
+    let policy = IssuePolicy::new(
+        &disabled,
+        &[],
+        &BTreeMap::new(),
+        MisraVersion::C2012,
+        vec![],
+    )
+    .unwrap();
+    let matrix = compliance_matrix(
+        MisraVersion::C2012,
+        &grp,
+        &policy,
+        &BTreeMap::from([("misra-c2012-2.3", 2), ("misra-c2012-8.4", 1)]),
+        &BTreeMap::from([("misra-c2012-8.4", 1)]),
+    );
+    let row = |name: &str| matrix.iter().find(|r| r.guideline == name).unwrap();
+    assert_eq!(row("Rule 1.1").compliance, Compliance::NotChecked);
+    assert_eq!(row("Rule 1.1").support, Support::CompilerOnly);
+    assert_eq!(row("Rule 2.3").compliance, Compliance::Violations);
+    assert_eq!(row("Rule 8.4").compliance, Compliance::Deviations);
+    assert_eq!(row("Rule 15.5").compliance, Compliance::Disapplied);
+    assert_eq!(row("Rule 9.1").compliance, Compliance::Compliant);
+    for name in ["Dir 1.1", "Dir 2.1", "Dir 3.1"] {
+        assert_eq!(row(name).support, Support::NotChecked);
+        assert_eq!(row(name).compliance, Compliance::NotChecked);
+    }
+    assert_eq!(row("Dir 4.9").recategorization, Some(GrpCategory::Required));
+    assert_eq!(
+        grp.classification(catalog::lookup("misra-c2012-Dir-4.9").unwrap()),
+        Some(Classification::Required)
+    );
+    assert_eq!(
+        grp.classification(catalog::lookup("misra-c2012-2.3").unwrap()),
+        Some(Classification::Advisory)
+    );
+    assert!(!matrix.iter().any(|r| r.guideline == "Rule 23.1"));
+}
@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+
+use globset::{Glob, GlobMatcher};
+
+use crate::{
+    catalog::{self, CustomEntry, MisraVersion},
+    result::Severity,
+};
+
+/// A key of `disabled_issues`, `enabled_only` or `severity_overrides`: a DeepSource
+/// issue code (`CXX-W3651`), a cppcheck id (`nullPointer`) or a glob over either
+/// (`misra-c2012-21.*`).
+struct Key {
+    key: String,
+    matcher: GlobMatcher,
+}
+
+impl Key {
+    fn new(key: &str) -> Result<Self, globset::Error> {
+        Ok(Key {
+            key: key.to_string(),
+            matcher: Glob::new(key)?.compile_matcher(),
+        })
+    }
+
+    fn is_match(&self, id: &str, code: &str) -> bool {
+        self.matcher.is_match(id) || self.matcher.is_match(code)
+    }
+
+    fn is_exact(&self, id: &str, code: &str) -> bool {
+        self.key == id || self.key == code
+    }
+}
+
+/// Which checks are reported, and with which severity.
+pub struct IssuePolicy {
+    disabled: Vec<Key>,
+    /// `None` enables every check not disabled.
+    enabled_only: Option<Vec<Key>>,
+    severity_overrides: Vec<(Key, Severity)>,
+    misra_version: MisraVersion,
+    /// Entries of the repository's addons and rules.
+    custom: Vec<CustomEntry>,
+}
+
+impl IssuePolicy {
+    pub fn new(
+        disabled: &[String],
+        enabled_only: &[String],
+        severity_overrides: &BTreeMap<String, Severity>,
+        misra_version: MisraVersion,
+        custom: Vec<CustomEntry>,
+    ) -> Result<Self, globset::Error> {
+        let keys = |keys: &[String]| {
+            keys.iter()
+                .map(|k| Key::new(k))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(IssuePolicy {
+            disabled: keys(disabled)?,
+            enabled_only: if enabled_only.is_empty() {
+                None
+            } else {
+                Some(keys(enabled_only)?)
+            },
+            severity_overrides: severity_overrides
+                .iter()
+                .map(|(k, severity)| Key::new(k).map(|key| (key, *severity)))
+                .collect::<Result<_, _>>()?,
+            misra_version,
+            custom,
+        })
+    }
+
+    /// Whether the cppcheck check `id`, reported as `code`, is reported at all.
+    pub fn is_enabled(&self, id: &str, code: &str) -> bool {
+        // rules of a later MISRA C version than the selected one
+        if catalog::lookup(id)
+            .and_then(|e| e.misra_since)
+            .is_some_and(|since| since > self.misra_version)
+        {
+            return false;
+        }
+        let enabled = self
+            .enabled_only
+            .as_ref()
+            .is_none_or(|keys| keys.iter().any(|k| k.is_match(id, code)));
+        enabled && !self.disabled.iter().any(|k| k.is_match(id, code))
+    }
+
+    /// The configured severity of `id`, if any. An exact id or code wins over a glob.
+    pub fn severity(&self, id: &str, code: &str) -> Option<Severity> {
+        let overrides = &self.severity_overrides;
+        overrides
+            .iter()
+            .find(|(k, _)| k.is_exact(id, code))
+            .or_else(|| overrides.iter().find(|(k, _)| k.is_match(id, code)))
+            .map(|(_, severity)| *severity)
+    }
+
+    /// Mapped cppcheck ids which are not reported, passed to cppcheck as suppressions
+    /// so that no time is spent on them. cppcheck matches suppressed ids as globs, which
+    /// suppresses the findings of an addon by the prefix of their ids.
+    pub fn suppressions(&self) -> Vec<String> {
+        let custom = self
+            .custom
+            .iter()
+            .filter(|e| !self.is_enabled(&e.id, &e.code))
+            // a longer prefix of the same addon may still be enabled
+            .filter(|e| {
+                !self.custom.iter().any(|other| {
+                    other.id.len() > e.id.len()
+                        && e.matches(&other.id)
+                        && self.is_enabled(&other.id, &other.code)
+                })
+            })
+            .map(|e| match e.prefix {
+                true => format!("{}*", e.id),
+                false => e.id.clone(),
+            });
+        catalog::entries()
+            .iter()
+            .filter(|e| !self.is_enabled(e.id, e.code))
+            .map(|e| e.id.to_string())
+            .chain(custom)
+            .collect()
+    }
+
+    /// Keys matching no known cppcheck id or issue code, most likely typos.
+    pub fn unknown_keys(&self) -> Vec<&str> {
+        self.disabled
+            .iter()
+            .chain(self.enabled_only.iter().flatten())
+            .chain(self.severity_overrides.iter().map(|(k, _)| k))
+            .filter(|k| {
+                !catalog::entries()
+                    .iter()
+                    .chain([&catalog::UNMAPPED])
+                    .any(|e| k.is_match(e.id, e.code))
+                    && !self.custom.iter().any(|e| k.is_match(&e.id, &e.code))
+            })
+            .map(|k| k.key.as_str())
+            .collect()
+    }
+}
+
+#[test]
+fn t_policy() {
+    let policy = IssuePolicy::new(
+        &["CXX-W3651".into(), "misra-c2012-21.*".into()],
+        &[],
+        &BTreeMap::from([
+            ("misra-c2012-*".into(), Severity::Minor),
+            ("misra-c2012-2.3".into(), Severity::Critical),
+            ("typo".into(), Severity::Major),
+        ]),
+        MisraVersion::C2012,
+        vec![],
+    )
+    .unwrap();
+    assert!(!policy.is_enabled("misra-c2012-21.3", "CXX-W3129"));
+    assert!(!policy.is_enabled("nullPointer", "CXX-W3651"));
+    assert!(policy.is_enabled("misra-c2012-2.3", "CXX-W3007"));
+    assert_eq!(
+        policy.severity("misra-c2012-2.3", "CXX-W3007"),
+        Some(Severity::Critical)
+    );
+    assert_eq!(
+        policy.severity("misra-c2012-8.4", "CXX-W3034"),
+        Some(Severity::Minor)
+    );
+    assert_eq!(policy.severity("nullPointerDefaultArg", "CXX-W3652"), None);
+    let suppressions = policy.suppressions();
+    assert!(suppressions.contains(&"misra-c2012-21.1".to_string()));
+    assert!(!suppressions.contains(&"misra-c2012-2.3".to_string()));
+    assert_eq!(policy.unknown_keys(), ["typo"]);
+
+    assert!(!policy.is_enabled("misra-c2012-23.1", "CXX-W3194"));
+
+    let policy = IssuePolicy::new(
+        &[],
+        &["misra-*".into()],
+        &BTreeMap::new(),
+        MisraVersion::C2023,
+        vec![],
+    )
+    .unwrap();
+    assert!(!policy.is_enabled("nullPointer", "CXX-W3651"));
+    assert!(policy.is_enabled("misra-c2012-2.3", "CXX-W3007"));
+    assert!(policy.is_enabled("misra-c2012-23.1", "CXX-W3194"));
+}
+
+#[test]
+fn t_custom_suppressions() {
+    let entry = |id: &str, code: &str, prefix| CustomEntry {
+        id: id.to_string(),
+        code: code.to_string(),
+        title: String::new(),
+        description: None,
+        prefix,
+    };
+    let custom = vec![
+        entry("house-noGoto", "CXX-W4201", true),
+        entry("house-", "CXX-W4200", true),
+        entry("noAlloca", "CXX-W4100", false),
+    ];
+    let policy = |disabled: &[&str]| {
+        let disabled: Vec<_> = disabled.iter().map(|d| d.to_string()).collect();
+        IssuePolicy::new(
+            &disabled,
+            &[],
+            &BTreeMap::new(),
+            MisraVersion::C2012,
+            custom.clone(),
+        )
+        .unwrap()
+    };
+    let custom_suppressions = |policy: &IssuePolicy| {
+        let suppressions = policy.suppressions().into_iter();
+        suppressions
+            .filter(|id| catalog::lookup(id).is_none())
+            .collect::<Vec<_>>()
+    };
+
+    let disabled = policy(&["CXX-W4100", "CXX-W4200"]);
+    assert!(disabled.unknown_keys().is_empty());
+    // `house-noGoto` findings are still reported
+    assert_eq!(custom_suppressions(&disabled), ["noAlloca"]);
+    let disabled = policy(&["house-*"]);
+    assert_eq!(custom_suppressions(&disabled), ["house-noGoto*", "house-*"]);
+}
@@ -1,6 +1,14 @@
 #![allow(dead_code)]
+use std::collections::{BTreeMap, HashSet};
+
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    catalog::{self, Classification},
+    cppcheck,
+    misra::GuidelineCompliance,
+};
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Mark {
     pub line: u32,
@@ -19,9 +27,208 @@ pub struct Location {
     pub position: Position,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Critical,
+    Major,
+    Minor,
+}
+
+impl Severity {
+    /// Default severity for a cppcheck severity class.
+    pub fn from_cppcheck(severity: &str) -> Self {
+        match severity {
+            "error" => Severity::Critical,
+            "warning" => Severity::Major,
+            _ => Severity::Minor,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Issue {
     pub issue_text: String,
     pub issue_code: String,
     pub location: Location,
+    pub severity: Severity,
+    /// Set for issues raised in files matching the repository's `test_patterns`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub test_code: bool,
+    /// The deviation record covering a MISRA violation, which is then not a failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deviation: Option<String>,
+    /// The MISRA classification of the rule violated, after re-categorization.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub misra_classification: Option<Classification>,
+}
+
+impl Issue {
+    /// Whether the issue fails the analysis. MISRA violations fail it unless deviated or of a
+    /// rule less strict than `misra_fail_on`, findings of other checks always do.
+    pub fn fails(&self, misra_fail_on: Classification) -> bool {
+        match self.misra_classification {
+            Some(classification) => self.deviation.is_none() && classification <= misra_fail_on,
+            None => true,
+        }
+    }
+}
+
+/// The findings kept for the report, each once although files analyzed by more than one
+/// invocation report them again.
+#[derive(Debug, Default)]
+pub struct Findings {
+    seen: HashSet<(String, String, u32, u32, String)>,
+    /// Kept findings of checks missing from the issue catalog, by cppcheck id.
+    pub unmapped_checks: BTreeMap<String, usize>,
+}
+
+impl Findings {
+    /// Records a finding of the check `id` reported as `code`, returning whether it is new.
+    pub fn insert(
+        &mut self,
+        id: &str,
+        code: &str,
+        location: &cppcheck::Location,
+        issue_text: &str,
+    ) -> bool {
+        let new = self.seen.insert((
+            code.to_string(),
+            location.file.clone(),
+            location.line,
+            location.column,
+            issue_text.to_string(),
+        ));
+        if new && code == catalog::UNMAPPED.code {
+            *self.unmapped_checks.entry(id.to_string()).or_default() += 1;
+        }
+        new
+    }
+}
+
+/// Where the list of files to analyze came from.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryMode {
+    /// The `files` of `analysis_config.json`.
+    #[default]
+    AnalysisConfig,
+    /// Walking the repository, honouring git's ignore rules.
+    Walk,
+}
+
+/// Details about the run that are not issues themselves.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ExtraData {
+    pub discovery_mode: DiscoveryMode,
+    /// Include directories passed to cppcheck, relative to the repository root.
+    pub include_dirs: Vec<String>,
+    /// Quoted includes that could not be resolved, with the files including them.
+    pub unresolved_includes: BTreeMap<String, Vec<String>>,
+    /// cppcheck library configs passed to cppcheck.
+    pub libraries: Vec<String>,
+    /// C/C++ files which were not analyzed, and why.
+    pub skipped_files: Vec<SkippedFile>,
+    /// Findings in the analyzed files of cppcheck checks missing from the issue catalog, by
+    /// cppcheck id, each counted once.
+    pub unmapped_checks: BTreeMap<String, usize>,
+    /// MISRA Compliance:2020 guideline compliance summary, when C files were checked
+    /// against MISRA C.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub misra_compliance: Option<Vec<GuidelineCompliance>>,
+    /// MISRA deviation records which covered no violation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unused_deviations: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+/// A problem with the run itself, rather than with the analyzed code.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnalysisError {
+    /// Human readable message.
+    pub hmrn: String,
+    pub level: ErrorLevel,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorLevel {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AnalysisResult {
+    pub issues: Vec<Issue>,
+    pub is_passed: bool,
+    pub errors: Vec<AnalysisError>,
+    pub extra_data: ExtraData,
+}
+
+#[test]
+fn t_fails() {
+    let issue = |misra_classification, deviation: Option<&str>| Issue {
+        issue_text: String::new(),
+        issue_code: String::new(),
+        location: Location {
+            path: String::new(),
+            position: Position {
+                begin: Mark { line: 1, column: 1 },
+                end: Mark { line: 1, column: 1 },
+            },
+        },
+        severity: Severity::Minor,
+        test_code: false,
+        deviation: deviation.map(String::from),
+        misra_classification,
+    };
+    let required = Classification::Required;
+    assert!(issue(None, None).fails(required));
+    assert!(issue(Some(Classification::Mandatory), None).fails(required));
+    assert!(issue(Some(Classification::Required), None).fails(required));
+    assert!(!issue(Some(Classification::Advisory), None).fails(required));
+    assert!(!issue(Some(Classification::Required), Some("DEV-1")).fails(required));
+    assert!(issue(Some(Classification::Advisory), None).fails(Classification::Advisory));
+}
+
+#[test]
+fn t_findings() {
+    let location = |line| cppcheck::Location {
+        file: "src/a.c".to_string(),
+        line,
+        column: 1,
+    };
+    let mut findings = Findings::default();
+    let unmapped = catalog::UNMAPPED.code;
+    assert!(findings.insert(
+        "brandNewCheck",
+        unmapped,
+        &location(1),
+        "msg [brandNewCheck]"
+    ));
+    // the same finding of a second invocation
+    assert!(!findings.insert(
+        "brandNewCheck",
+        unmapped,
+        &location(1),
+        "msg [brandNewCheck]"
+    ));
+    assert!(findings.insert(
+        "brandNewCheck",
+        unmapped,
+        &location(2),
+        "msg [brandNewCheck]"
+    ));
+    assert!(findings.insert("nullPointer", "CXX-W2000", &location(1), "msg"));
+    assert!(!findings.insert("nullPointer", "CXX-W2000", &location(1), "msg"));
+    assert_eq!(
+        findings.unmapped_checks,
+        [("brandNewCheck".to_string(), 2)].into()
+    );
 }
@@ -0,0 +1,378 @@
+//! Custom rules, token patterns matched by cppcheck's `--rule-file` support.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use quick_xml::escape::escape;
+use serde::Deserialize;
+
+use crate::{
+    catalog::{self, CustomEntry, Entry},
+    config,
+};
+
+/// The issue the findings of a custom rule are reported as.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
+pub struct RuleIssue {
+    /// Issue code defined by the team, e.g. `CXX-W4100`.
+    pub code: String,
+    /// What the rule enforces and why, shown with the issue.
+    pub description: String,
+}
+
+/// A cppcheck rule XML file of the repository.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
+pub struct RuleFile {
+    /// Relative to the repository root.
+    pub path: PathBuf,
+    /// Issues by id of the rules in the file.
+    pub issues: BTreeMap<String, RuleIssue>,
+}
+
+/// cppcheck severity of the findings of a rule.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSeverity {
+    Error,
+    Warning,
+    Style,
+    Performance,
+    Portability,
+    Information,
+}
+
+impl RuleSeverity {
+    fn as_str(self) -> &'static str {
+        match self {
+            RuleSeverity::Error => "error",
+            RuleSeverity::Warning => "warning",
+            RuleSeverity::Style => "style",
+            RuleSeverity::Performance => "performance",
+            RuleSeverity::Portability => "portability",
+            RuleSeverity::Information => "information",
+        }
+    }
+}
+
+/// The token list a rule pattern is matched against.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum TokenList {
+    /// After simplification.
+    #[default]
+    Simple,
+    /// After preprocessing, before simplification.
+    Normal,
+    /// Before preprocessing.
+    Raw,
+    /// The `#define` directives.
+    Define,
+}
+
+impl TokenList {
+    fn as_str(self) -> &'static str {
+        match self {
+            TokenList::Simple => "simple",
+            TokenList::Normal => "normal",
+            TokenList::Raw => "raw",
+            TokenList::Define => "define",
+        }
+    }
+}
+
+/// A rule defined in the config.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[cfg_attr(test, schemars(deny_unknown_fields))]
+pub struct InlineRule {
+    /// cppcheck id of the findings, e.g. `noAlloca`.
+    pub id: String,
+    /// PCRE matched against the token list, tokens separated by single spaces,
+    /// e.g. `\bgets \(`.
+    pub pattern: String,
+    /// Message of the findings, also the title of the issue.
+    pub message: String,
+    pub severity: RuleSeverity,
+    /// Token list matched, `simple` (after simplification), `normal`, `raw` or `define`.
+    #[serde(default)]
+    pub tokenlist: TokenList,
+    #[serde(flatten)]
+    pub issue: RuleIssue,
+}
+
+/// The `<rule>` elements of a rule file, read for their ids.
+#[derive(Deserialize, Debug, Default)]
+struct RulesXml {
+    #[serde(default)]
+    rule: Vec<RuleXml>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RuleXml {
+    message: MessageXml,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessageXml {
+    /// cppcheck reports rules without one as `rule`.
+    id: Option<String>,
+    summary: Option<String>,
+}
+
+/// The custom rules of the repository, with catalog entries for their codes.
+#[derive(Debug, Default)]
+pub struct CustomRules {
+    /// Absolute paths of the rule files to pass to `--rule-file`.
+    pub files: Vec<PathBuf>,
+    /// Rule file holding the inline rules, if any.
+    pub inline: Option<String>,
+    entries: Vec<CustomEntry>,
+}
+
+impl CustomRules {
+    /// Loads the rules, returning the problems with the mapping of their ids to issues.
+    /// Their ids and codes must be unique, and differ from those of the catalog and of
+    /// `addons`, the entries of the repository's addons.
+    pub fn load(
+        code_path: &Path,
+        files: &[RuleFile],
+        inline: &[InlineRule],
+        addons: &[CustomEntry],
+    ) -> Result<(Self, Vec<String>), String> {
+        let mut rules = CustomRules::default();
+        let mut problems = vec![];
+        let mut codes = BTreeSet::new();
+        let mut ids = BTreeSet::new();
+        let mut unique = |id: &str| match ids.insert(id.to_string()) {
+            true => Ok(()),
+            false => Err(format!("rule `{id}`: the id is used twice")),
+        };
+        let mut add = |id: &str, title: &str, issue: &RuleIssue| {
+            let invalid = |what: &str| Err(format!("rule `{id}`: {what}"));
+            if catalog::lookup(id).is_some() {
+                return invalid("the id is that of a built-in check");
+            }
+            if let Some(addon) = addons.iter().find(|e| e.matches(id)) {
+                return invalid(&format!("the id is taken by the addon ids `{}*`", addon.id));
+            }
+            if catalog::by_code(&issue.code).is_some() {
+                return invalid(&format!("`{}` is the code of a built-in check", issue.code));
+            }
+            if addons.iter().any(|e| e.code == issue.code) {
+                return invalid(&format!("`{}` is the code of an addon", issue.code));
+            }
+            if !codes.insert(issue.code.clone()) {
+                return invalid(&format!("`{}` is used twice", issue.code));
+            }
+            Ok(CustomEntry {
+                id: id.to_string(),
+                code: issue.code.clone(),
+                title: title.to_string(),
+                description: Some(issue.description.clone()),
+                prefix: false,
+            })
+        };
+
+        for file in files {
+            let path = config::repository_file(code_path, &file.path)?;
+            let src = std::fs::read_to_string(&path)
+                .map_err(|err| format!("Failed to read `{}`: {err}", file.path.display()))?;
+            let summaries =
+                rule_summaries(&src).map_err(|err| format!("`{}`: {err}", file.path.display()))?;
+            for (id, summary) in &summaries {
+                unique(id)?;
+                match file.issues.get(id) {
+                    Some(issue) => rules.entries.push(add(id, summary, issue)?),
+                    None => problems.push(format!(
+                        "`{}`: rule `{id}` has no issue code, its findings are unmapped",
+                        file.path.display()
+                    )),
+                }
+            }
+            for id in file.issues.keys().filter(|id| !summaries.contains_key(*id)) {
+                problems.push(format!("`{}` has no rule `{id}`", file.path.display()));
+            }
+            rules.files.push(path);
+        }
+
+        for rule in inline {
+            unique(&rule.id)?;
+            rules
+                .entries
+                .push(add(&rule.id, &rule.message, &rule.issue)?);
+        }
+        if !inline.is_empty() {
+            rules.inline = Some(inline_xml(inline));
+        }
+        Ok((rules, problems))
+    }
+
+    /// The entry for the findings of the rule `id`.
+    pub fn lookup(&self, id: &str) -> Option<Entry<'_>> {
+        self.entries
+            .iter()
+            .find(|e| e.matches(id))
+            .map(CustomEntry::entry)
+    }
+
+    pub fn entries(&self) -> &[CustomEntry] {
+        &self.entries
+    }
+}
+
+/// The summary of each rule of a rule file, by id. A file holds one `<rule>`, or
+/// several in a `<rules>` element.
+fn rule_summaries(src: &str) -> Result<BTreeMap<String, String>, quick_xml::DeError> {
+    let rules = if src.contains("<rules") {
+        quick_xml::de::from_str::<RulesXml>(src)?.rule
+    } else {
+        vec![quick_xml::de::from_str::<RuleXml>(src)?]
+    };
+    Ok(rules
+        .into_iter()
+        .map(|rule| {
+            let id = rule.message.id.unwrap_or_else(|| "rule".to_string());
+            let summary = rule.message.summary.unwrap_or_else(|| id.clone());
+            (id, summary)
+        })
+        .collect())
+}
+
+/// A rule file with the inline rules.
+fn inline_xml(rules: &[InlineRule]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\"?>\n<rules>\n");
+    for rule in rules {
+        xml.push_str(&format!(
+            "  <rule version=\"1\">\n    <tokenlist>{}</tokenlist>\n    <pattern>{}</pattern>\n    \
+             <message>\n      <id>{}</id>\n      <severity>{}</severity>\n      \
+             <summary>{}</summary>\n    </message>\n  </rule>\n",
+            rule.tokenlist.as_str(),
+            escape(&rule.pattern),
+            escape(&rule.id),
+            rule.severity.as_str(),
+            escape(&rule.message),
+        ));
+    }
+    xml.push_str("</rules>\n");
+    xml
+}
+
+#[test]
+fn t_custom_rules() {
+    let summaries = rule_summaries(
+        r#"<?xml version="1.0"?>
+<rules>
+  <rule version="1">
+    <pattern>\bgets \(</pattern>
+    <message><id>noGets</id><severity>error</severity><summary>gets is banned</summary></message>
+  </rule>
+  <rule version="1">
+    <pattern>\bsprintf \(</pattern>
+    <message><id>noSprintf</id><severity>warning</severity></message>
+  </rule>
+</rules>"#,
+    )
+    .unwrap();
+    assert_eq!(
+        summaries,
+        BTreeMap::from([
+            ("noGets".to_string(), "gets is banned".to_string()),
+            ("noSprintf".to_string(), "noSprintf".to_string()),
+        ])
+    );
+
+    let rule = InlineRule {
+        id: "noAlloca".to_string(),
+        pattern: r"\balloca \(".to_string(),
+        message: "alloca & friends are banned".to_string(),
+        severity: RuleSeverity::Warning,
+        tokenlist: TokenList::default(),
+        issue: RuleIssue {
+            code: "CXX-W4100".to_string(),
+            description: "Stack allocations of unknown size overflow.".to_string(),
+        },
+    };
+    let (rules, problems) =
+        CustomRules::load(Path::new("."), &[], std::slice::from_ref(&rule), &[]).unwrap();
+    assert!(problems.is_empty());
+    let entry = rules.lookup("noAlloca").unwrap();
+    assert_eq!(entry.code, "CXX-W4100");
+    assert_eq!(
+        entry.description,
+        Some("Stack allocations of unknown size overflow.")
+    );
+    assert!(rules.lookup("noAllocaX").is_none());
+    let xml = rules.inline.unwrap();
+    assert!(xml.contains("<summary>alloca &amp; friends are banned</summary>"));
+    assert_eq!(
+        rule_summaries(&xml).unwrap()["noAlloca"],
+        "alloca & friends are banned"
+    );
+
+    let mut other = rule.clone();
+    other.id = "noVla".to_string();
+    let err =
+        CustomRules::load(Path::new("."), &[], &[rule.clone(), other.clone()], &[]).unwrap_err();
+    assert_eq!(err, "rule `noVla`: `CXX-W4100` is used twice");
+    other.id = rule.id.clone();
+    other.issue.code = "CXX-W4101".to_string();
+    let err = CustomRules::load(Path::new("."), &[], &[rule.clone(), other], &[]).unwrap_err();
+    assert_eq!(err, "rule `noAlloca`: the id is used twice");
+
+    let load = |id: &str, code: &str, addons: &[CustomEntry]| {
+        let mut rule = rule.clone();
+        rule.id = id.to_string();
+        rule.issue.code = code.to_string();
+        CustomRules::load(Path::new("."), &[], &[rule], addons).unwrap_err()
+    };
+    assert_eq!(
+        load("nullPointer", "CXX-W4100", &[]),
+        "rule `nullPointer`: the id is that of a built-in check"
+    );
+    let addon = CustomEntry {
+        id: "house-".to_string(),
+        code: "CXX-W4200".to_string(),
+        title: String::new(),
+        description: None,
+        prefix: true,
+    };
+    assert_eq!(
+        load("house-alloca", "CXX-W4100", std::slice::from_ref(&addon)),
+        "rule `house-alloca`: the id is taken by the addon ids `house-*`"
+    );
+    assert_eq!(
+        load("noAlloca", "CXX-W4200", &[addon]),
+        "rule `noAlloca`: `CXX-W4200` is the code of an addon"
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let code_path = dir.path().join("code");
+    std::fs::create_dir(&code_path).unwrap();
+    std::fs::write(dir.path().join("rules.xml"), "<rule/>").unwrap();
+    let file = RuleFile {
+        path: "../rules.xml".into(),
+        issues: BTreeMap::new(),
+    };
+    let err = CustomRules::load(&code_path, &[file], &[], &[]).unwrap_err();
+    assert_eq!(err, "`../rules.xml` is outside of the repository");
+
+    // a rule file and an inline rule with the same id
+    std::fs::write(
+        code_path.join("rules.xml"),
+        "<rule><pattern>alloca</pattern><message><id>noAlloca</id></message></rule>",
+    )
+    .unwrap();
+    let file = RuleFile {
+        path: "rules.xml".into(),
+        issues: BTreeMap::new(),
+    };
+    let err = CustomRules::load(&code_path, &[file], &[rule], &[]).unwrap_err();
+    assert_eq!(err, "rule `noAlloca`: the id is used twice");
+}
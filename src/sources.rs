@@ -0,0 +1,397 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use ignore::WalkBuilder;
+
+use crate::{deepsource::Patterns, language::Extensions};
+
+/// Markers identifying generated sources, matched case-insensitively in the comments heading
+/// a file. Generic phrases are anchored so that remarks such as "do not edit this table
+/// without..." in hand-written files do not match.
+const GENERATED_MARKERS: &[&str] = &[
+    "do not edit!",
+    "do not edit.",
+    "@generated",
+    "code generated by",
+    "file generated by",
+    "this file was generated",
+    "this file is generated",
+    "automatically generated",
+    "autogenerated",
+    "a bison parser, made by",
+    "a lexical scanner generated by flex",
+];
+
+/// Only the head of a file is searched for generated code markers.
+const GENERATED_MARKER_WINDOW: usize = 4096;
+
+/// Limits applied to every candidate file.
+#[derive(Debug)]
+pub struct Limits {
+    pub max_file_size: u64,
+    pub max_lines: Option<usize>,
+    pub skip_generated: bool,
+    /// Extra markers, on top of [`GENERATED_MARKERS`].
+    pub generated_markers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    Excluded,
+    /// A symbolic link, or a path through one, resolving to `target` inside the repository.
+    /// `target` is relative to the repository root.
+    Link {
+        target: PathBuf,
+    },
+    /// A symbolic link, or a path through one, resolving to `target` outside the repository.
+    OutsideRepository {
+        target: PathBuf,
+    },
+    NotAFile,
+    TooLarge {
+        size: u64,
+        limit: u64,
+    },
+    TooManyLines {
+        lines: usize,
+        limit: usize,
+    },
+    Generated {
+        marker: String,
+    },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Excluded => write!(f, "matches `exclude_patterns`"),
+            SkipReason::Link { target } => {
+                write!(f, "links to `{}`, analyzed there", target.display())
+            }
+            SkipReason::OutsideRepository { target } => write!(
+                f,
+                "links to `{}`, outside of the repository",
+                target.display()
+            ),
+            SkipReason::NotAFile => write!(f, "not a regular file"),
+            SkipReason::TooLarge { size, limit } => {
+                write!(f, "{size} bytes exceeds the limit of {limit} bytes")
+            }
+            SkipReason::TooManyLines { lines, limit } => {
+                write!(f, "{lines} lines exceeds the limit of {limit} lines")
+            }
+            SkipReason::Generated { marker } => write!(f, "generated code (`{marker}`)"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub files: Vec<PathBuf>,
+    pub skipped: Vec<(PathBuf, SkipReason)>,
+}
+
+/// Picks the C/C++ files to analyze out of a list of candidates.
+pub struct Selector<'a> {
+    pub code_path: &'a Path,
+    pub extensions: &'a Extensions,
+    pub patterns: &'a Patterns,
+    pub limits: &'a Limits,
+}
+
+impl Selector<'_> {
+    pub fn select(&self, candidates: impl IntoIterator<Item = PathBuf>) -> Selection {
+        let mut selection = Selection::default();
+        let mut seen = HashSet::new();
+        let canonical_root = self
+            .code_path
+            .canonicalize()
+            .unwrap_or_else(|_| self.code_path.to_path_buf());
+        for file in candidates {
+            // files of other languages are not worth reporting
+            if self.extensions.kind(&file).is_none() {
+                continue;
+            }
+            match self.admit(&file, &canonical_root) {
+                Ok(real) => {
+                    if seen.insert(real.clone()) {
+                        selection.files.push(real.clone());
+                    }
+                    // issues are attributed to the file the link points to
+                    if real != file {
+                        let target = real.strip_prefix(self.code_path).unwrap_or(&real);
+                        let reason = SkipReason::Link {
+                            target: target.to_path_buf(),
+                        };
+                        log::info!("Skipping `{}`: {reason}", file.display());
+                        selection.skipped.push((file, reason));
+                    }
+                }
+                Err(reason) => {
+                    log::info!("Skipping `{}`: {reason}", file.display());
+                    selection.skipped.push((file, reason));
+                }
+            }
+        }
+        selection
+    }
+
+    fn admit(&self, file: &Path, canonical_root: &Path) -> Result<PathBuf, SkipReason> {
+        self.check_excluded(file)?;
+        let real = self.resolve(file, canonical_root)?;
+        if real != file {
+            self.check_excluded(&real)?;
+        }
+        self.check(&real)?;
+        Ok(real)
+    }
+
+    fn check_excluded(&self, file: &Path) -> Result<(), SkipReason> {
+        if self
+            .patterns
+            .is_excluded(file.strip_prefix(self.code_path).unwrap_or(file))
+        {
+            return Err(SkipReason::Excluded);
+        }
+        Ok(())
+    }
+
+    /// Resolves symbolic links, returning the path of the real file below `code_path`.
+    fn resolve(&self, file: &Path, canonical_root: &Path) -> Result<PathBuf, SkipReason> {
+        let canonical = file.canonicalize().map_err(|_| SkipReason::NotAFile)?;
+        if canonical == file {
+            return Ok(canonical);
+        }
+        match canonical.strip_prefix(canonical_root) {
+            Ok(relative) => Ok(self.code_path.join(relative)),
+            Err(_) => Err(SkipReason::OutsideRepository { target: canonical }),
+        }
+    }
+
+    fn check(&self, file: &Path) -> Result<(), SkipReason> {
+        let metadata = file.metadata().map_err(|_| SkipReason::NotAFile)?;
+        if !metadata.is_file() {
+            return Err(SkipReason::NotAFile);
+        }
+        let limit = self.limits.max_file_size;
+        if metadata.len() > limit {
+            return Err(SkipReason::TooLarge {
+                size: metadata.len(),
+                limit,
+            });
+        }
+        if let Some(limit) = self.limits.max_lines {
+            let lines = std::fs::read(file)
+                .map(|src| count_lines(&src))
+                .unwrap_or_default();
+            if lines > limit {
+                return Err(SkipReason::TooManyLines { lines, limit });
+            }
+        }
+        if self.limits.skip_generated {
+            if let Some(marker) = self.generated_marker(file) {
+                return Err(SkipReason::Generated { marker });
+            }
+        }
+        Ok(())
+    }
+
+    fn generated_marker(&self, file: &Path) -> Option<String> {
+        let mut head = Vec::with_capacity(GENERATED_MARKER_WINDOW);
+        std::fs::File::open(file)
+            .ok()?
+            .take(GENERATED_MARKER_WINDOW as u64)
+            .read_to_end(&mut head)
+            .ok()?;
+        generated_marker(
+            &String::from_utf8_lossy(&head),
+            &self.limits.generated_markers,
+        )
+    }
+}
+
+/// Lists every file below `code_path` not ignored by git (`.gitignore` files,
+/// `.git/info/exclude` and the global excludes), used when DeepSource did not provide
+/// the list of files to analyze.
+pub fn walk(code_path: &Path) -> Vec<PathBuf> {
+    WalkBuilder::new(code_path)
+        .hidden(false)
+        .ignore(false)
+        .parents(false)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_some_and(|t| !t.is_dir()))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn count_lines(src: &[u8]) -> usize {
+    let newlines = src.iter().filter(|&&b| b == b'\n').count();
+    // the last line need not end with a newline
+    newlines + usize::from(src.last().is_some_and(|&b| b != b'\n'))
+}
+
+/// The comment lines heading `src`, up to its first line of code.
+fn leading_comments(src: &str) -> Vec<&str> {
+    let mut comments = vec![];
+    let mut in_block = false;
+    for line in src.lines().map(str::trim) {
+        if in_block {
+            in_block = !line.contains("*/");
+        } else if line.is_empty() {
+            continue;
+        } else if let Some(rest) = line.strip_prefix("/*") {
+            in_block = !rest.contains("*/");
+        } else if !line.starts_with("//") {
+            break;
+        }
+        comments.push(line);
+    }
+    comments
+}
+
+/// Finds a generated code marker in the comments heading `head`.
+fn generated_marker(head: &str, extra: &[String]) -> Option<String> {
+    let head = head.to_lowercase();
+    let comments = leading_comments(&head);
+    GENERATED_MARKERS
+        .iter()
+        .map(|m| m.to_string())
+        .chain(extra.iter().map(|m| m.to_lowercase()))
+        .find(|marker| comments.iter().any(|l| l.contains(marker.as_str())))
+}
+
+#[test]
+fn t_generated_marker() {
+    let protobuf =
+        "// Generated by the protocol buffer compiler.  DO NOT EDIT!\n// source: a.proto\n";
+    let bison = "/* A Bison parser, made by GNU Bison 3.8.2.  */\n";
+    let handwritten = "#include <stdio.h>\nconst char *s = \"generated by hand\";\n";
+    let remarks = "/*\n * Documentation generated by doxygen.\n */\n// Do not edit this table without updating the docs\n";
+    let late = "int x;\n// Code generated by hand. DO NOT EDIT.\n";
+    assert_eq!(
+        generated_marker(protobuf, &[]).as_deref(),
+        Some("do not edit!")
+    );
+    assert_eq!(
+        generated_marker(bison, &[]).as_deref(),
+        Some("a bison parser, made by")
+    );
+    assert_eq!(generated_marker(handwritten, &[]), None);
+    assert_eq!(generated_marker(remarks, &[]), None);
+    assert_eq!(generated_marker(late, &[]), None);
+    assert_eq!(
+        generated_marker(
+            "/* Copyright\n */\n\n// Code generated by stringer. DO NOT EDIT.\n",
+            &[]
+        )
+        .as_deref(),
+        Some("do not edit.")
+    );
+    assert_eq!(count_lines(b"a\nb"), 2);
+    assert_eq!(count_lines(b"a\nb\n"), 2);
+    assert_eq!(count_lines(b""), 0);
+    assert_eq!(
+        generated_marker(
+            "/* Created with CodeGen 2 */",
+            &["created with codegen".into()]
+        )
+        .as_deref(),
+        Some("created with codegen")
+    );
+}
+
+#[test]
+fn t_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let dir = tempfile::tempdir().unwrap();
+    let code_path = dir.path().join("code");
+    std::fs::create_dir_all(code_path.join("src")).unwrap();
+    std::fs::create_dir_all(code_path.join("lib")).unwrap();
+    std::fs::write(code_path.join("src/real.c"), "int x;\n").unwrap();
+    std::fs::write(dir.path().join("outside.c"), "int y;\n").unwrap();
+    symlink("../src/real.c", code_path.join("lib/link.c")).unwrap();
+    symlink(dir.path().join("outside.c"), code_path.join("lib/out.c")).unwrap();
+
+    let extensions = Extensions::new(&Default::default());
+    let patterns = crate::deepsource::DeepSourceConfig::default()
+        .patterns()
+        .unwrap();
+    let limits = Limits {
+        max_file_size: u64::MAX,
+        max_lines: None,
+        skip_generated: true,
+        generated_markers: vec![],
+    };
+    let selector = Selector {
+        code_path: &code_path,
+        extensions: &extensions,
+        patterns: &patterns,
+        limits: &limits,
+    };
+    let selection =
+        selector.select(["lib/link.c", "src/real.c", "lib/out.c"].map(|f| code_path.join(f)));
+    // both paths to the file are analyzed once, under the path of the real file
+    assert_eq!(selection.files, [code_path.join("src/real.c")]);
+    assert_eq!(
+        selection.skipped,
+        [
+            (
+                code_path.join("lib/link.c"),
+                SkipReason::Link {
+                    target: PathBuf::from("src/real.c")
+                }
+            ),
+            (
+                code_path.join("lib/out.c"),
+                SkipReason::OutsideRepository {
+                    target: dir.path().canonicalize().unwrap().join("outside.c")
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn t_walk() {
+    let dir = tempfile::tempdir().unwrap();
+    let code_path = dir.path();
+    for file in [
+        ".git/info/exclude",
+        ".gitignore",
+        "a.c",
+        "a.o",
+        "keep.o",
+        "secret.c",
+        "build/b.c",
+        "sub/.gitignore",
+        "sub/gen.c",
+        "sub/main.c",
+    ] {
+        let path = code_path.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+    std::fs::write(code_path.join(".git/info/exclude"), "secret.c\n").unwrap();
+    std::fs::write(code_path.join(".gitignore"), "*.o\n!keep.o\nbuild/\n").unwrap();
+    std::fs::write(code_path.join("sub/.gitignore"), "*.c\n!main.c\n").unwrap();
+    assert_eq!(
+        walk(code_path),
+        [
+            ".gitignore",
+            "a.c",
+            "keep.o",
+            "sub/.gitignore",
+            "sub/main.c"
+        ]
+        .map(|f| code_path.join(f))
+    );
+}
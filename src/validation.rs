@@ -0,0 +1,304 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::config::AnalyzerConfig;
+
+/// How problems in `analysis_config.json` are handled.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigValidation {
+    /// Record problems as warnings in the output and carry on.
+    #[default]
+    Lenient,
+    /// Fail the run.
+    Strict,
+}
+
+/// One step of a path into a JSON document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// A problem found in `analysis_config.json`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub path: Vec<Segment>,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`$")?;
+        for segment in &self.path {
+            match segment {
+                Segment::Key(key) => write!(f, ".{key}")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        write!(f, "`")?;
+        if let Some(line) = self.line {
+            write!(f, " (line {line})")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct Validated {
+    /// `None` when the config could not be deserialized at all.
+    pub config: Option<AnalyzerConfig>,
+    pub mode: ConfigValidation,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parses `analysis_config.json`, reporting unknown fields, type mismatches and missing files.
+pub fn validate(src: &str) -> Validated {
+    // read on its own, so that the mode is known even if the rest of the config is broken
+    let mode = serde_json::from_str::<serde_json::Value>(src)
+        .ok()
+        .and_then(|v| v.pointer("/analyzer_meta/config_validation").cloned())
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    let mut unknown = vec![];
+    let mut record = |path: serde_ignored::Path| unknown.push(ignored_path(&path));
+    let mut de = serde_json::Deserializer::from_str(src);
+    let parsed = serde_path_to_error::deserialize::<_, AnalyzerConfig>(
+        serde_ignored::Deserializer::new(&mut de, &mut record),
+    )
+    .map_err(|err| (error_path(err.path()), err.into_inner()))
+    .and_then(|config| de.end().map(|()| config).map_err(|err| (vec![], err)));
+
+    let mut diagnostics: Vec<_> = unknown
+        .into_iter()
+        .map(|path| Diagnostic {
+            line: line_of(src, &path),
+            path,
+            message: "unknown field".to_string(),
+        })
+        .collect();
+    let config = match parsed {
+        Ok(config) => {
+            diagnostics.extend(config.missing_files().map(|(index, file)| {
+                let path = vec![Segment::Key("files".to_string()), Segment::Index(index)];
+                Diagnostic {
+                    line: line_of(src, &path),
+                    path,
+                    message: format!("`{}` does not exist", file.display()),
+                }
+            }));
+            Some(config)
+        }
+        Err((path, err)) => {
+            // the message of a serde_json error ends with its position, which is reported separately
+            let message = err.to_string();
+            let message = message
+                .rfind(" at line ")
+                .map_or(message.as_str(), |at| &message[..at]);
+            diagnostics.push(Diagnostic {
+                path,
+                line: Some(err.line()),
+                message: message.to_string(),
+            });
+            None
+        }
+    };
+    Validated {
+        config,
+        mode,
+        diagnostics,
+    }
+}
+
+fn error_path(path: &serde_path_to_error::Path) -> Vec<Segment> {
+    path.iter()
+        .filter_map(|segment| match segment {
+            serde_path_to_error::Segment::Seq { index } => Some(Segment::Index(*index)),
+            serde_path_to_error::Segment::Map { key }
+            | serde_path_to_error::Segment::Enum { variant: key } => {
+                Some(Segment::Key(key.clone()))
+            }
+            serde_path_to_error::Segment::Unknown => None,
+        })
+        .collect()
+}
+
+fn ignored_path(path: &serde_ignored::Path) -> Vec<Segment> {
+    let mut segments = match path {
+        serde_ignored::Path::Root => return vec![],
+        serde_ignored::Path::Seq { parent, .. }
+        | serde_ignored::Path::Map { parent, .. }
+        | serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_path(parent),
+    };
+    match path {
+        serde_ignored::Path::Seq { index, .. } => segments.push(Segment::Index(*index)),
+        serde_ignored::Path::Map { key, .. } => segments.push(Segment::Key(key.clone())),
+        _ => {}
+    }
+    segments
+}
+
+/// Finds the line, 1-based, of the key or array element at `path` in a JSON document.
+pub fn line_of(src: &str, path: &[Segment]) -> Option<usize> {
+    let mut cursor = Cursor { src, pos: 0 };
+    let mut target = 0;
+    for segment in path {
+        cursor.skip_ws();
+        match segment {
+            Segment::Key(key) => {
+                cursor.expect(b'{')?;
+                loop {
+                    cursor.skip_ws();
+                    target = cursor.pos;
+                    let name = cursor.string()?;
+                    cursor.skip_ws();
+                    cursor.expect(b':')?;
+                    if name == *key {
+                        break;
+                    }
+                    cursor.skip_ws();
+                    cursor.value()?;
+                    cursor.skip_ws();
+                    cursor.expect(b',')?;
+                }
+            }
+            Segment::Index(index) => {
+                cursor.expect(b'[')?;
+                for _ in 0..*index {
+                    cursor.skip_ws();
+                    cursor.value()?;
+                    cursor.skip_ws();
+                    cursor.expect(b',')?;
+                }
+                cursor.skip_ws();
+                target = cursor.pos;
+            }
+        }
+    }
+    Some(src[..target].matches('\n').count() + 1)
+}
+
+struct Cursor<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.pos += 1)
+    }
+
+    /// Reads a string literal, without unescaping it.
+    fn string(&mut self) -> Option<&'a str> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => break,
+                _ => self.pos += 1,
+            }
+        }
+        self.pos += 1;
+        self.src.get(start..self.pos - 1)
+    }
+
+    /// Skips over any value.
+    fn value(&mut self) -> Option<()> {
+        let mut depth = 0usize;
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.string()?;
+                }
+                b'{' | b'[' => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                b'}' | b']' if depth > 0 => {
+                    depth -= 1;
+                    self.pos += 1;
+                }
+                b',' | b'}' | b']' if depth == 0 => return Some(()),
+                _ => self.pos += 1,
+            }
+            if depth == 0 && self.peek().is_none_or(|b| b",}]".contains(&b)) {
+                return Some(());
+            }
+        }
+    }
+}
+
+#[test]
+fn t_validate() {
+    let src = r#"{
+    "files": [
+        "/nonexistent/a.c"
+    ],
+    "analyzer_meta": {
+        "name": "cxx",
+        "enabled": true,
+        "overrides": [
+            { "paths": ["lib"], "stdd": "c99" }
+        ],
+        "language_standard": "c99",
+        "auto_libraries": "yes"
+    }
+}"#;
+    let validated = validate(src);
+    assert!(validated.config.is_none());
+    let rendered: Vec<_> = validated
+        .diagnostics
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        rendered,
+        [
+            "`$.analyzer_meta.overrides[0].stdd` (line 9): unknown field",
+            "`$.analyzer_meta.language_standard` (line 11): unknown field",
+            "`$.analyzer_meta.auto_libraries` (line 12): invalid type: string \"yes\", expected a boolean",
+        ]
+    );
+
+    let validated = validate(&src.replace("\"yes\"", "false"));
+    assert!(validated.config.is_some());
+    assert_eq!(
+        validated.diagnostics[2].to_string(),
+        "`$.files[0]` (line 3): `/nonexistent/a.c` does not exist"
+    );
+}
+
+#[test]
+fn t_schema_up_to_date() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/schema/analysis_config.schema.json"
+    );
+    let schema =
+        serde_json::to_string_pretty(&schemars::schema_for!(AnalyzerConfig)).unwrap() + "\n";
+    if std::env::var_os("UPDATE_SCHEMA").is_some() {
+        std::fs::write(path, &schema).unwrap();
+    }
+    assert!(
+        std::fs::read_to_string(path).is_ok_and(|s| s == schema),
+        "`{path}` is outdated, run `UPDATE_SCHEMA=1 cargo test` to regenerate it"
+    );
+}